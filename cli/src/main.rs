@@ -0,0 +1,362 @@
+use std::{collections::HashMap, env, error::Error, path::PathBuf, process};
+
+use anchor_interface_syn::{
+    try_parse_crate_path, try_parse_field_type, try_parse_path_list, AttrOptions, ExternType,
+    FeatureOptions, Generator, GeneratorOptions,
+};
+use clap::{Args, Parser, Subcommand};
+
+#[derive(Parser)]
+#[command(version, about = "Anchor program interface generator")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate program interface module from IDL.
+    Generate(GenerateArgs),
+    /// Check account layouts against account dumps.
+    Validate(ValidateArgs),
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    /// Path to the IDL, IDL account data or `solana account --output json` dump.
+    #[arg(long)]
+    idl: PathBuf,
+    /// Path to the out module file.
+    #[arg(long)]
+    out: PathBuf,
+    #[command(flatten)]
+    opts: GeneratorArgs,
+}
+
+#[derive(Args)]
+struct ValidateArgs {
+    /// Path to the IDL, IDL account data or `solana account --output json` dump.
+    #[arg(long)]
+    idl: PathBuf,
+    /// Directory of account dumps (raw data or `solana account --output json` output).
+    #[arg(long)]
+    dumps: PathBuf,
+    #[command(flatten)]
+    opts: GeneratorArgs,
+}
+
+/// Same options as `program!(..)` macro has.
+#[derive(Args)]
+struct GeneratorArgs {
+    /// Program address, required by the legacy IDLs without one.
+    #[arg(long)]
+    program_id: Option<String>,
+    /// List of structs with implemented `borsh` always.
+    #[arg(long, value_delimiter = ',')]
+    with_borsh: Vec<String>,
+    /// List of zero-copy structs.
+    #[arg(long, value_delimiter = ',')]
+    zero_copy: Vec<String>,
+    /// List of `repr(packed)` structs.
+    #[arg(long, value_delimiter = ',')]
+    packed: Vec<String>,
+    /// Generate only the listed instructions.
+    #[arg(long, value_delimiter = ',')]
+    only_instructions: Vec<String>,
+    /// Skip the listed instructions.
+    #[arg(long, value_delimiter = ',')]
+    skip_instructions: Vec<String>,
+    /// Generate only the listed accounts.
+    #[arg(long, value_delimiter = ',')]
+    only_accounts: Vec<String>,
+    /// Skip the listed accounts.
+    #[arg(long, value_delimiter = ',')]
+    skip_accounts: Vec<String>,
+    /// Additional attribute as `NAME[,NAME..]:ATTR`, e.g. `MarketStatus,MarketType:repr(u8)`.
+    #[arg(long)]
+    attr: Vec<String>,
+    /// Generate `<Instruction>::builder()` for every instruction.
+    #[arg(long)]
+    builders: bool,
+    /// Generate a `#[cfg(test)]` module with round-trip tests of the interface.
+    #[arg(long)]
+    gen_tests: bool,
+    /// Generate `Arbitrary` implementations and the `fuzz` module.
+    #[arg(long)]
+    fuzz: bool,
+    /// Generate the `processor` module with the program trait and entrypoint.
+    #[arg(long)]
+    processor: bool,
+    /// Generate `SysvarInstruction` loading the program instructions from the instructions sysvar.
+    #[arg(long)]
+    introspection: bool,
+    /// Cargo feature gating generated items as `GATE=FEATURE`, gates are `instruction`,
+    /// `state`, `types`, `error`, `builders`, `account_indexes`, `display`, `fuzz`,
+    /// `introspection` and `processor`.
+    #[arg(long)]
+    feature: Vec<String>,
+    /// Fixed address of instruction account as `NAME=ADDRESS`.
+    #[arg(long)]
+    known_account: Vec<String>,
+    /// Existing Rust type used instead of IDL type as `NAME=PATH[,copy][,default][,pod]`.
+    #[arg(long)]
+    extern_type: Vec<String>,
+    /// Field type override as `Type::field=TYPE` or `instruction::arg=TYPE`.
+    #[arg(long)]
+    field_type: Vec<String>,
+
+    /// Path to the `solana_program` crate.
+    #[arg(long)]
+    solana_program: Option<String>,
+    /// Path to the module with `Pubkey`.
+    #[arg(long)]
+    solana_pubkey: Option<String>,
+    /// Path to the module with `Instruction` and `AccountMeta`.
+    #[arg(long)]
+    solana_instruction: Option<String>,
+    /// Path to the module with `ProgramError`.
+    #[arg(long)]
+    solana_program_error: Option<String>,
+    /// Path to the module with `DecodeError`.
+    #[arg(long)]
+    solana_decode_error: Option<String>,
+    /// Path to the module with `AccountInfo`.
+    #[arg(long)]
+    solana_account_info: Option<String>,
+    /// Path to the instructions sysvar module.
+    #[arg(long)]
+    solana_instructions_sysvar: Option<String>,
+    /// Path to the `borsh` crate.
+    #[arg(long)]
+    borsh: Option<String>,
+    /// Path to the `bytemuck` crate.
+    #[arg(long)]
+    bytemuck: Option<String>,
+    /// Path to the `num_derive` crate.
+    #[arg(long)]
+    num_derive: Option<String>,
+    /// Path to the `num_traits` crate.
+    #[arg(long)]
+    num_traits: Option<String>,
+    /// Path to the `thiserror` crate.
+    #[arg(long)]
+    thiserror: Option<String>,
+    /// Path to the `arbitrary` crate.
+    #[arg(long)]
+    arbitrary: Option<String>,
+    /// Path to the `anchor_interface` crate.
+    #[arg(long)]
+    anchor_interface: Option<String>,
+}
+
+impl GeneratorArgs {
+    fn into_options(self, idl: String) -> Result<GeneratorOptions, Box<dyn Error>> {
+        let path_list = |list: Vec<String>| {
+            (!list.is_empty())
+                .then(|| try_parse_path_list(&list.iter().map(String::as_str).collect::<Vec<_>>()))
+                .transpose()
+        };
+        let crate_path =
+            |path: Option<String>| path.as_deref().map(try_parse_crate_path).transpose();
+
+        let attr = self
+            .attr
+            .iter()
+            .map(|attr| {
+                let (names, attr) = attr
+                    .split_once(':')
+                    .ok_or_else(|| format!("invalid attr `{attr}`, expected `NAME:ATTR`"))?;
+                let names: Vec<_> = names.split(',').map(str::trim).collect();
+                Ok(AttrOptions {
+                    attr: vec![syn::parse_str(attr)?],
+                    names: try_parse_path_list(&names)?,
+                })
+            })
+            .collect::<Result<_, Box<dyn Error>>>()?;
+        let known_accounts = self
+            .known_account
+            .iter()
+            .map(|known_account| {
+                let (name, address) = known_account.split_once('=').ok_or_else(|| {
+                    format!("invalid known account `{known_account}`, expected `NAME=ADDRESS`")
+                })?;
+                Ok((name.to_string(), address.to_string()))
+            })
+            .collect::<Result<HashMap<_, _>, Box<dyn Error>>>()?;
+        let extern_types = self
+            .extern_type
+            .iter()
+            .map(|extern_type| {
+                let (name, ty) = extern_type.split_once('=').ok_or_else(|| {
+                    format!("invalid extern type `{extern_type}`, expected `NAME=PATH`")
+                })?;
+                let mut ty = ty.split(',').map(str::trim);
+                let mut out = ExternType::try_new(ty.next().unwrap_or_default())?;
+                for property in ty {
+                    out = match property {
+                        "copy" => out.copy(),
+                        "default" => out.default(),
+                        "pod" => out.pod(),
+                        _ => {
+                            return Err(format!("unknown extern type property `{property}`").into())
+                        }
+                    };
+                }
+                Ok((name.to_string(), out))
+            })
+            .collect::<Result<HashMap<_, _>, Box<dyn Error>>>()?;
+
+        let field_types = self
+            .field_type
+            .iter()
+            .map(|field_type| {
+                let (field, ty) = field_type.split_once('=').ok_or_else(|| {
+                    format!("invalid field type `{field_type}`, expected `Type::field=TYPE`")
+                })?;
+                Ok((field.to_string(), try_parse_field_type(ty)?))
+            })
+            .collect::<Result<HashMap<_, _>, Box<dyn Error>>>()?;
+
+        let mut features = FeatureOptions::default();
+        for feature in &self.feature {
+            let (gate, name) = feature
+                .split_once('=')
+                .ok_or_else(|| format!("invalid feature `{feature}`, expected `GATE=FEATURE`"))?;
+            let gate = match gate {
+                "instruction" => &mut features.instruction,
+                "state" => &mut features.state,
+                "types" => &mut features.types,
+                "error" => &mut features.error,
+                "builders" => &mut features.builders,
+                "account_indexes" => &mut features.account_indexes,
+                "display" => &mut features.display,
+                "fuzz" => &mut features.fuzz,
+                "introspection" => &mut features.introspection,
+                "processor" => &mut features.processor,
+                _ => return Err(format!("unknown feature gate `{gate}`").into()),
+            };
+            *gate = Some(name.to_string());
+        }
+
+        Ok(GeneratorOptions {
+            idl,
+            program_id: self.program_id,
+            with_borsh: path_list(self.with_borsh)?,
+            zero_copy: path_list(self.zero_copy)?,
+            packed: path_list(self.packed)?,
+            only_instructions: path_list(self.only_instructions)?,
+            skip_instructions: path_list(self.skip_instructions)?,
+            only_accounts: path_list(self.only_accounts)?,
+            skip_accounts: path_list(self.skip_accounts)?,
+            attr,
+            builders: self.builders,
+            gen_tests: self.gen_tests,
+            fuzz: self.fuzz,
+            processor: self.processor,
+            introspection: self.introspection,
+            features,
+            known_accounts: (!known_accounts.is_empty()).then_some(known_accounts),
+            extern_types: (!extern_types.is_empty()).then_some(extern_types),
+            field_types: (!field_types.is_empty()).then_some(field_types),
+            solana_program: crate_path(self.solana_program)?,
+            solana_pubkey: crate_path(self.solana_pubkey)?,
+            solana_instruction: crate_path(self.solana_instruction)?,
+            solana_program_error: crate_path(self.solana_program_error)?,
+            solana_decode_error: crate_path(self.solana_decode_error)?,
+            solana_account_info: crate_path(self.solana_account_info)?,
+            solana_instructions_sysvar: crate_path(self.solana_instructions_sysvar)?,
+            borsh: crate_path(self.borsh)?,
+            bytemuck: crate_path(self.bytemuck)?,
+            num_derive: crate_path(self.num_derive)?,
+            num_traits: crate_path(self.num_traits)?,
+            thiserror: crate_path(self.thiserror)?,
+            arbitrary: crate_path(self.arbitrary)?,
+            anchor_interface: crate_path(self.anchor_interface)?,
+            ..Default::default()
+        })
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    match Cli::parse().command {
+        Command::Generate(GenerateArgs { idl, out, opts }) => {
+            let opts = opts.into_options(idl.display().to_string())?;
+            Generator::try_new(opts, env::current_dir()?)?.write_program_file(&out)?;
+        }
+        Command::Validate(ValidateArgs { idl, dumps, opts }) => {
+            let opts = opts.into_options(idl.display().to_string())?;
+            let report =
+                Generator::try_new(opts, env::current_dir()?)?.validate_accounts(&dumps)?;
+            print!("{report}");
+            if !report.is_ok() {
+                process::exit(1);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[&str]) -> Result<GeneratorOptions, Box<dyn Error>> {
+        let cli = Cli::try_parse_from(
+            [
+                "anchor-interface",
+                "generate",
+                "--idl",
+                "idl.json",
+                "--out",
+                "out.rs",
+            ]
+            .iter()
+            .chain(args),
+        )?;
+        let Command::Generate(args) = cli.command else {
+            unreachable!()
+        };
+        args.opts.into_options("idl.json".to_string())
+    }
+
+    #[test]
+    fn valid_options() {
+        let opts = options(&[
+            "--zero-copy",
+            "TickArray,Tick",
+            "--attr",
+            "MarketType:repr(u8)",
+            "--extern-type",
+            "Fraction=my_crate::Fraction,copy",
+            "--borsh",
+            "::my_borsh",
+        ])
+        .unwrap();
+        assert_eq!(opts.zero_copy.unwrap().len(), 2);
+        assert_eq!(opts.extern_types.unwrap().len(), 1);
+        assert!(opts.borsh.is_some());
+    }
+
+    #[test]
+    fn invalid_path_list() {
+        assert!(options(&["--zero-copy", "Tick-Array"]).is_err());
+        assert!(options(&["--zero-copy", "a::b"]).is_err());
+        assert!(options(&["--attr", "Market Type:repr(u8)"]).is_err());
+    }
+
+    #[test]
+    fn invalid_crate_path() {
+        assert!(options(&["--borsh", "my borsh"]).is_err());
+    }
+
+    #[test]
+    fn invalid_extern_type() {
+        assert!(options(&["--extern-type", "Fraction=my crate::Fraction"]).is_err());
+        assert!(options(&["--extern-type", "Fraction=my_crate::Fraction,clone"]).is_err());
+    }
+
+    #[test]
+    fn invalid_field_type() {
+        assert!(options(&["--field-type", "Pool::price=u64 u64"]).is_err());
+    }
+}
@@ -2,7 +2,16 @@
 
 // We can generate code in-place
 #[cfg(not(feature = "gen-file"))]
-anchor_interface_gen::program!(idl = "ratex.json", with_borsh(Order));
+anchor_interface_gen::program!(
+    idl = "ratex.json",
+    with_borsh(Order),
+    builders,
+    gen_tests,
+    fuzz,
+    processor,
+    introspection,
+    features(fuzz = "fuzz"),
+);
 
 // Or we can generate included rust-file (easier to debug)
 #[cfg(feature = "gen-file")]
@@ -11,6 +20,12 @@ anchor_interface_gen::program!(
     out_mod = "_gen_",
     idl = "ratex.json",
     with_borsh(Order),
+    builders,
+    gen_tests,
+    fuzz,
+    processor,
+    introspection,
+    features(fuzz = "fuzz"),
     attr(
         names(MarketStatus, EpochUpdateStatus, MarginType, MarketType),
         attr(repr(u8)),
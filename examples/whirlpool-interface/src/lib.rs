@@ -5,7 +5,16 @@
 anchor_interface_gen::program!(
     idl = "whirlpool.json",
     zero_copy(TickArray, Tick),
-    packed(TickArray, Tick)
+    packed(TickArray, Tick),
+    builders,
+    gen_tests,
+    fuzz,
+    processor,
+    introspection,
+    features(fuzz = "fuzz"),
+    // the crate's own `solana_program`, so the generated tests compile with configured paths
+    solana_pubkey = "::solana_program::pubkey",
+    solana_account_info = "::solana_program::account_info",
 );
 
 // Or we can generate included rust-file (easier to debug)
@@ -15,7 +24,16 @@ anchor_interface_gen::program!(
     out_mod = "_gen_",
     idl = "whirlpool.json",
     zero_copy(TickArray, Tick),
-    packed(TickArray, Tick)
+    packed(TickArray, Tick),
+    builders,
+    gen_tests,
+    fuzz,
+    processor,
+    introspection,
+    features(fuzz = "fuzz"),
+    // the crate's own `solana_program`, so the generated tests compile with configured paths
+    solana_pubkey = "::solana_program::pubkey",
+    solana_account_info = "::solana_program::account_info",
 );
 
 solana_program::declare_id!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
@@ -1,5 +1,5 @@
 use darling::{ast::NestedMeta, FromMeta};
-use proc_macro::TokenStream;
+use proc_macro::{Span, TokenStream};
 
 use anchor_interface_syn::{Generator, GeneratorOptions};
 
@@ -11,6 +11,17 @@ pub fn program(input: TokenStream) -> TokenStream {
             return darling::Error::from(e).write_errors().into();
         }
     };
-    let opts = GeneratorOptions::from_list(&attr_args).expect("parse options");
-    Generator::from(opts).generate().into()
+    let opts = match GeneratorOptions::from_list(&attr_args) {
+        Ok(opts) => opts,
+        Err(e) => {
+            return e.write_errors().into();
+        }
+    };
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR");
+    match Generator::try_new(opts, manifest_dir) {
+        Ok(gen) => gen.generate().into(),
+        Err(err) => syn::Error::new(Span::call_site().into(), err)
+            .to_compile_error()
+            .into(),
+    }
 }
@@ -1,19 +1,146 @@
-use std::io::{Result, Write};
+use std::io::{Read, Write};
+
+use crate::errors::AccountError;
+
+/// Discriminator written by Anchor into the closed accounts.
+pub const CLOSED_ACCOUNT_DISCRIMINATOR: [u8; 8] = [255; 8];
+
+/// Max account data size permitted by the system program, 10 MiB.
+pub const MAX_PERMITTED_DATA_LENGTH: u64 = 10 * 1024 * 1024;
+
+/// Reader of the `data` followed by `padding` zero bytes.
+pub fn zero_padded(data: &[u8], padding: u64) -> impl Read + '_ {
+    data.chain(std::io::repeat(0).take(padding))
+}
 
 pub trait Account: Sized {
     const DISCRIMINATOR: &'static [u8];
+
+    /// Program owning the account, the bytes of its address, so the trait doesn't
+    /// depend on the `Pubkey` type the interface is generated with.
+    const OWNER: [u8; 32];
+
+    /// Checks the account is owned by [`Account::OWNER`], `owner` is the address bytes
+    /// of the account owner, e.g. `info.owner.to_bytes()` of any `AccountInfo`.
+    fn check_owner(owner: [u8; 32]) -> Result<(), AccountError> {
+        if owner != Self::OWNER {
+            return Err(AccountError::OwnerMismatch {
+                expected: Self::OWNER,
+                got: owner,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks the account data starts with [`Account::DISCRIMINATOR`].
+    fn check_discriminator(data: &[u8]) -> Result<(), AccountError> {
+        let len = Self::DISCRIMINATOR.len();
+        if data.len() < len {
+            return Err(AccountError::InsufficientData {
+                needed: len,
+                got: data.len(),
+            });
+        }
+        let got = &data[..len];
+        if got != Self::DISCRIMINATOR {
+            if got.starts_with(&CLOSED_ACCOUNT_DISCRIMINATOR) {
+                return Err(AccountError::Closed);
+            }
+            return Err(AccountError::DiscriminatorMismatch {
+                expected: Self::DISCRIMINATOR,
+                got: got.to_vec(),
+            });
+        }
+        Ok(())
+    }
 }
 
 pub trait AccountSerialize: Account {
-    fn try_serialize<W: Write>(&self, writer: &mut W) -> Result<()>;
+    fn try_serialize<W: Write>(&self, writer: &mut W) -> Result<(), AccountError>;
 }
 
+/// Borsh account decoding.
+///
+/// The data starts with [`Account::DISCRIMINATOR`] followed by the account,
+/// the variants differ in handling of the bytes after the account:
+/// - [`try_deserialize`](Self::try_deserialize) ignores them and doesn't advance `data`;
+/// - [`try_deserialize_stream`](Self::try_deserialize_stream) advances `data` past the account,
+///   so the following data can be decoded;
+/// - [`try_deserialize_strict`](Self::try_deserialize_strict) rejects non-zero trailing bytes,
+///   zero padding of the allocated account space is accepted;
+/// - [`try_deserialize_lenient`](Self::try_deserialize_lenient) ignores them and decodes
+///   the data shorter than the account (e.g. of an older program version) as zero-filled,
+///   so the missing fields are zeroes, `None`, empty or the first enum variants.
+///   The zero padding is limited by the size of the zeroed account (or
+///   [`MAX_PERMITTED_DATA_LENGTH`] if the size is unknown, e.g. of generic arrays),
+///   so an oversized length prefix fails at the end of the data instead of decoding
+///   zeroes forever.
 pub trait AccountDeserialize: Account {
-    fn try_deserialize(data: &mut &[u8]) -> Result<Self>;
+    fn try_deserialize_stream(data: &mut &[u8]) -> Result<Self, AccountError>;
+
+    fn try_deserialize_lenient(data: &[u8]) -> Result<Self, AccountError>;
+
+    fn try_deserialize(data: &mut &[u8]) -> Result<Self, AccountError> {
+        Self::try_deserialize_stream(&mut &data[..])
+    }
+
+    fn try_deserialize_strict(data: &[u8]) -> Result<Self, AccountError> {
+        let mut rest = data;
+        let account = Self::try_deserialize_stream(&mut rest)?;
+        if rest.iter().any(|&byte| byte != 0) {
+            return Err(AccountError::TrailingBytes(rest.len()));
+        }
+        Ok(account)
+    }
 }
 
-pub trait PodAccount: Account + Copy {
-    fn try_init_bytes(bytes: &mut [u8]) -> Result<&mut Self>;
-    fn try_from_bytes_mut(bytes: &mut [u8]) -> Result<&mut Self>;
-    fn try_from_bytes(bytes: &[u8]) -> Result<&Self>;
+pub trait PodAccount: Account + Copy + 'static {
+    /// Writes [`Account::DISCRIMINATOR`] into the account data, the data with
+    /// a non-zero discriminator fails with [`AccountError::AlreadyInitialized`].
+    fn try_init_bytes(bytes: &mut [u8]) -> Result<&mut Self, AccountError> {
+        let len = Self::DISCRIMINATOR.len();
+        if bytes
+            .get(..len)
+            .is_some_and(|discriminator| discriminator.iter().any(|&byte| byte != 0))
+        {
+            return Err(AccountError::AlreadyInitialized);
+        }
+        Self::try_init_bytes_forced(bytes)
+    }
+
+    /// Writes [`Account::DISCRIMINATOR`] into the account data overwriting
+    /// the existing one, the account bytes are kept.
+    fn try_init_bytes_forced(bytes: &mut [u8]) -> Result<&mut Self, AccountError>;
+
+    fn try_from_bytes_mut(bytes: &mut [u8]) -> Result<&mut Self, AccountError>;
+
+    fn try_from_bytes(bytes: &[u8]) -> Result<&Self, AccountError>;
+
+    /// Copies the account out of the account data, which may be unaligned.
+    fn try_read(bytes: &[u8]) -> Result<Self, AccountError>;
+
+    /// Account data: [`Account::DISCRIMINATOR`] followed by the account bytes.
+    fn to_account_data(&self) -> Vec<u8>;
+}
+
+#[cfg(all(test, feature = "borsh"))]
+mod tests {
+    use borsh::BorshDeserialize;
+
+    use super::*;
+
+    /// Decodes the zero-padded length prefix of a `Vec<u8>` of `len` bytes.
+    fn decode_padded(len: u32) -> std::io::Result<Vec<u8>> {
+        let data = len.to_le_bytes();
+        let padding = MAX_PERMITTED_DATA_LENGTH - data.len() as u64;
+        let mut reader = zero_padded(&data, padding);
+        Vec::<u8>::deserialize_reader(&mut reader)
+    }
+
+    #[test]
+    fn zero_padding_up_to_max_permitted_data_length() {
+        let len = MAX_PERMITTED_DATA_LENGTH as u32 - 4;
+        assert_eq!(decode_padded(len).unwrap().len(), len as usize);
+        assert!(decode_padded(len + 1).is_err());
+    }
 }
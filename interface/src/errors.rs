@@ -4,4 +4,86 @@ use thiserror::Error;
 pub enum TryAccountIndexesError {
     #[error("get index for account '{0}' with index {1} failure")]
     GetIndex(&'static str, usize),
+    #[error("account '{0}' with index {1} does not match its known address")]
+    KnownAccountMismatch(&'static str, usize),
+}
+
+#[derive(Debug, Error)]
+pub enum BuildInstructionError {
+    #[error("program id not set")]
+    MissingProgramId,
+    #[error("account '{0}' not set")]
+    MissingAccount(&'static str),
+    #[error("arg '{0}' not set")]
+    MissingArg(&'static str),
+}
+
+#[derive(Debug, Error)]
+#[error("invalid `{name}` value {value}")]
+pub struct InvalidEnumValue {
+    pub name: &'static str,
+    pub value: u8,
+}
+
+/// Account (de)serialization and loading error.
+///
+/// Some variants exist only with the `bytemuck` feature,
+/// so the enum is non-exhaustive to keep downstream matches compiling
+/// when the features are unified.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum AccountError {
+    #[error("invalid discriminator: got `{got:?}`, expected `{expected:?}`")]
+    DiscriminatorMismatch {
+        expected: &'static [u8],
+        got: Vec<u8>,
+    },
+    #[error("not enough data: got `{got}`, expected `{needed}`")]
+    InsufficientData { needed: usize, got: usize },
+    #[cfg(feature = "bytemuck")]
+    #[error("bytemuck cast failure: {0}")]
+    Bytemuck(bytemuck::PodCastError),
+    #[error("{0} trailing bytes")]
+    TrailingBytes(usize),
+    #[error("account closed")]
+    Closed,
+    #[error("account already initialized")]
+    AlreadyInitialized,
+    /// The owner addresses are bytes, so the error doesn't depend on the `Pubkey` type.
+    #[error("invalid owner: got `{got:?}`, expected `{expected:?}`")]
+    OwnerMismatch { expected: [u8; 32], got: [u8; 32] },
+    #[error("account not writable")]
+    NotWritable,
+    #[error("account data already borrowed")]
+    BorrowFailed,
+    /// Borsh (de)serialization failure, e.g. of malformed account data.
+    #[error("borsh failure: {0}")]
+    Borsh(std::io::Error),
+    /// Writer failure.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl From<AccountError> for std::io::Error {
+    fn from(err: AccountError) -> Self {
+        match err {
+            AccountError::Borsh(err) | AccountError::Io(err) => err,
+            err => std::io::Error::new(std::io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+#[cfg(feature = "solana-program")]
+impl From<AccountError> for solana_program::program_error::ProgramError {
+    fn from(err: AccountError) -> Self {
+        use solana_program::program_error::ProgramError;
+        match err {
+            AccountError::InsufficientData { .. } => ProgramError::AccountDataTooSmall,
+            AccountError::AlreadyInitialized => ProgramError::AccountAlreadyInitialized,
+            AccountError::OwnerMismatch { .. } => ProgramError::IllegalOwner,
+            AccountError::NotWritable => ProgramError::InvalidArgument,
+            AccountError::BorrowFailed => ProgramError::AccountBorrowFailed,
+            _ => ProgramError::InvalidAccountData,
+        }
+    }
 }
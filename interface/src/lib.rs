@@ -7,3 +7,38 @@ pub mod prelude {
 }
 
 pub use prelude::*;
+
+/// Dependencies of the generated code, so that a generated crate
+/// doesn't need to list them in its own manifest.
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "arbitrary")]
+    pub use arbitrary;
+    #[cfg(feature = "borsh")]
+    pub use borsh;
+    #[cfg(feature = "bytemuck")]
+    pub use bytemuck;
+    #[cfg(feature = "num-derive")]
+    pub use num_derive;
+    #[cfg(feature = "num-derive")]
+    pub use num_traits;
+    #[cfg(feature = "solana-program")]
+    pub use solana_program;
+    pub use thiserror;
+}
+
+/// Expands the items if `anchor-interface` is built with `solana-program`,
+/// so that generated code doesn't depend on the features of `anchor-interface`.
+#[cfg(feature = "solana-program")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cfg_solana_program {
+    ($($item:tt)*) => { $($item)* };
+}
+
+#[cfg(not(feature = "solana-program"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cfg_solana_program {
+    ($($item:tt)*) => {};
+}
@@ -1,11 +1,27 @@
-use proc_macro2::TokenStream;
-use quote::quote;
+use anchor_lang_idl::types::IdlType;
+use heck::ToSnakeCase;
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
 
+use crate::common::{discriminator_docs_gen, item_gen};
+use crate::layout::TypeLayouts;
 use crate::typedefs::typedef_gen;
-use crate::Generator;
+use crate::{CratePaths, Generator};
+
+/// Data size of the generated account tests if the account size is unknown,
+/// the max data increase of an account per instruction.
+const UNKNOWN_SIZE_TEST_DATA_LEN: usize = 10 * 1024;
 
 impl Generator {
     pub fn gen_accounts(&self) -> TokenStream {
+        let CratePaths {
+            anchor_interface,
+            borsh,
+            bytemuck,
+            ..
+        } = &self.paths;
+        let owner = self.program_id_array_or_error_gen();
+        let layouts = TypeLayouts::host(&self.idl.types);
         let accounts = self.idl.accounts.iter().map(|acc| {
             let ty = {
                 let Some(&idx) = self.account_type_idx_by_name.get(&acc.name) else {
@@ -14,39 +30,35 @@ impl Generator {
                 &self.idl.types[idx]
             };
 
-            let (typedef, name, opts) = typedef_gen(&self.idl.types, &self.typedef_opts, ty);
-
             let discriminator = &acc.discriminator;
+            let (typedef, name, opts) = typedef_gen(
+                &self.idl.types,
+                &self.typedef_opts,
+                &self.paths,
+                ty,
+                discriminator_docs_gen(discriminator),
+            );
+
+            let arbitrary = self.arbitrary_typedef_gen(ty, &opts);
             let discriminator_len = discriminator.len();
 
             let impl_account = quote! {
-                impl ::anchor_interface::Account for #name {
+                impl #anchor_interface::Account for #name {
                     const DISCRIMINATOR: &'static [u8] = &[#(#discriminator),*];
+                    const OWNER: [u8; 32] = #owner;
                 }
             };
+            let account_error = quote!(#anchor_interface::errors::AccountError);
             let check_discriminator = quote! {
-                if data.len() < #discriminator_len || &data[..#discriminator_len] != Self::DISCRIMINATOR {
-                    return Err(::std::io::Error::new(
-                        ::std::io::ErrorKind::InvalidData,
-                        format!(
-                            "invalid discriminator: got `{:?}`, expected `{:?}`",
-                            &data[..#discriminator_len.min(data.len())],
-                            Self::DISCRIMINATOR,
-                        ),
-                    ));
-                }
+                Self::check_discriminator(data)?;
             };
             let impl_serialize_and_deserialize = if opts.zero_copy {
                 let check_size = quote! {
                     if data.len() < #discriminator_len + std::mem::size_of::<Self>() {
-                        return Err(::std::io::Error::new(
-                            ::std::io::ErrorKind::InvalidData,
-                            format!(
-                                "not enough data: got `{}`, expected `{}`",
-                                data.len(),
-                                #discriminator_len + std::mem::size_of::<Self>(),
-                            ),
-                        ));
+                        return Err(#account_error::InsufficientData {
+                            needed: #discriminator_len + std::mem::size_of::<Self>(),
+                            got: data.len(),
+                        });
                     }
                 };
                 let from_bytes_checks = quote! {
@@ -57,73 +69,519 @@ impl Generator {
                     data[#discriminator_len..#discriminator_len + std::mem::size_of::<Self>()]
                 };
                 quote! {
-                    impl ::anchor_interface::PodAccount for #name {
-                        fn try_init_bytes(data: &mut [u8]) -> ::std::io::Result<&mut Self> {
-                            use ::anchor_interface::Account;
+                    impl #anchor_interface::AccountSerialize for #name {
+                        fn try_serialize<W: std::io::Write>(&self, writer: &mut W)
+                            -> Result<(), #account_error>
+                        {
+                            use #anchor_interface::Account;
+                            writer.write_all(Self::DISCRIMINATOR)?;
+                            writer.write_all(#bytemuck::bytes_of(self))?;
+                            Ok(())
+                        }
+                    }
+                    impl #anchor_interface::PodAccount for #name {
+                        fn try_init_bytes_forced(data: &mut [u8]) -> Result<&mut Self, #account_error> {
+                            use #anchor_interface::Account;
 
                             #check_size
 
                             data[..#discriminator_len].copy_from_slice(Self::DISCRIMINATOR);
 
                             let payload = &mut #payload;
-                            ::bytemuck::try_from_bytes_mut(payload).map_err(|err| {
-                                ::std::io::Error::new(::std::io::ErrorKind::InvalidData, err)
-                            })
+                            #bytemuck::try_from_bytes_mut(payload).map_err(#account_error::Bytemuck)
                         }
 
-                        fn try_from_bytes_mut(data: &mut [u8]) -> ::std::io::Result<&mut Self> {
-                            use ::anchor_interface::Account;
+                        fn try_from_bytes_mut(data: &mut [u8]) -> Result<&mut Self, #account_error> {
+                            use #anchor_interface::Account;
 
                             #from_bytes_checks
 
                             let payload = &mut #payload;
-                            ::bytemuck::try_from_bytes_mut(payload).map_err(|err| {
-                                ::std::io::Error::new(::std::io::ErrorKind::InvalidData, err)
-                            })
+                            #bytemuck::try_from_bytes_mut(payload).map_err(#account_error::Bytemuck)
                         }
 
-                        fn try_from_bytes(data: &[u8]) -> ::std::io::Result<&Self> {
-                            use ::anchor_interface::Account;
+                        fn try_from_bytes(data: &[u8]) -> Result<&Self, #account_error> {
+                            use #anchor_interface::Account;
 
                             #from_bytes_checks
 
                             let payload = &#payload;
-                            ::bytemuck::try_from_bytes(payload).map_err(|err| {
-                                ::std::io::Error::new(::std::io::ErrorKind::InvalidData, err)
-                            })
+                            #bytemuck::try_from_bytes(payload).map_err(#account_error::Bytemuck)
+                        }
+
+                        fn try_read(data: &[u8]) -> Result<Self, #account_error> {
+                            use #anchor_interface::Account;
+
+                            #from_bytes_checks
+
+                            let mut account = <Self as #bytemuck::Zeroable>::zeroed();
+                            #bytemuck::bytes_of_mut(&mut account).copy_from_slice(&#payload);
+                            Ok(account)
+                        }
+
+                        fn to_account_data(&self) -> Vec<u8> {
+                            use #anchor_interface::Account;
+                            [Self::DISCRIMINATOR, #bytemuck::bytes_of(self)].concat()
                         }
                     }
                 }
             } else {
+                // NOTE: the zero padding is limited by the size of the zeroed account
+                // (or the max account data size if the size is unknown),
+                // so the length prefixes of the data can't make it read forever
+                let padding = layouts
+                    .borsh_fixed_size(&IdlType::Defined {
+                        name: acc.name.clone(),
+                        generics: vec![],
+                    })
+                    .map_or_else(
+                        |_| quote!(#anchor_interface::account::MAX_PERMITTED_DATA_LENGTH),
+                        |size| {
+                            let size = size as u64;
+                            quote!(#size)
+                        },
+                    );
                 quote! {
-                    impl ::anchor_interface::AccountSerialize for #name {
+                    impl #anchor_interface::AccountSerialize for #name {
                         fn try_serialize<W: std::io::Write>(&self, writer: &mut W)
-                            -> std::io::Result<()>
+                            -> Result<(), #account_error>
                         {
-                            use ::anchor_interface::Account;
+                            use #anchor_interface::Account;
                             writer.write_all(Self::DISCRIMINATOR)?;
-                            ::borsh::BorshSerialize::serialize(self, writer)?;
+                            #borsh::BorshSerialize::serialize(self, writer)
+                                .map_err(#account_error::Borsh)?;
                             Ok(())
                         }
                     }
-                    impl ::anchor_interface::AccountDeserialize for #name {
-                        fn try_deserialize(data: &mut &[u8]) -> std::io::Result<Self> {
-                            use ::anchor_interface::Account;
+                    impl #anchor_interface::AccountDeserialize for #name {
+                        fn try_deserialize_stream(data: &mut &[u8]) -> Result<Self, #account_error> {
+                            use #anchor_interface::Account;
                             #check_discriminator
-                            let t = ::borsh::BorshDeserialize::deserialize(&mut &data[#discriminator_len..])?;
+                            let mut rest = &data[#discriminator_len..];
+                            let t = #borsh::BorshDeserialize::deserialize(&mut rest)
+                                .map_err(#account_error::Borsh)?;
+                            *data = rest;
                             Ok(t)
                         }
+
+                        fn try_deserialize_lenient(data: &[u8]) -> Result<Self, #account_error> {
+                            use #anchor_interface::Account;
+                            #check_discriminator
+                            let rest = &data[#discriminator_len..];
+                            let mut reader = #anchor_interface::account::zero_padded(rest, #padding);
+                            #borsh::BorshDeserialize::deserialize_reader(&mut reader)
+                                .map_err(#account_error::Borsh)
+                        }
                     }
                 }
             };
+            let account_info_loaders = self.account_info_loaders_gen(&name, opts.zero_copy);
             quote! {
                 #typedef
+                #arbitrary
                 #impl_account
                 #impl_serialize_and_deserialize
+                #account_info_loaders
             }
         });
         quote! {
             #(#accounts)*
         }
     }
+
+    /// Loaders of the account owned by the program from the configured `AccountInfo`.
+    fn account_info_loaders_gen(&self, name: &Ident, zero_copy: bool) -> TokenStream {
+        let anchor_interface = &self.paths.anchor_interface;
+        let account_info = self.paths.account_info();
+        let account_error = quote!(#anchor_interface::errors::AccountError);
+        let borrow_failed = quote!(|_| #account_error::BorrowFailed);
+        let loaders = if zero_copy {
+            quote! {
+                /// Borrows the account owned by the program in place,
+                /// like Anchor's `AccountLoader::load`.
+                pub fn load<'a>(
+                    info: &'a #account_info,
+                ) -> Result<::std::cell::Ref<'a, Self>, #account_error> {
+                    use #anchor_interface::{Account, PodAccount};
+                    Self::check_owner(info.owner.to_bytes())?;
+                    let data = info.data.try_borrow().map_err(#borrow_failed)?;
+                    Self::try_from_bytes(&data)?;
+                    // NOTE: the data is checked above
+                    Ok(::std::cell::Ref::map(data, |data| {
+                        Self::try_from_bytes(data).unwrap()
+                    }))
+                }
+
+                /// Borrows the writable account owned by the program in place,
+                /// like Anchor's `AccountLoader::load_mut`.
+                pub fn load_mut<'a>(
+                    info: &'a #account_info,
+                ) -> Result<::std::cell::RefMut<'a, Self>, #account_error> {
+                    use #anchor_interface::{Account, PodAccount};
+                    if !info.is_writable {
+                        return Err(#account_error::NotWritable);
+                    }
+                    Self::check_owner(info.owner.to_bytes())?;
+                    let mut data = info.data.try_borrow_mut().map_err(#borrow_failed)?;
+                    Self::try_from_bytes_mut(&mut data)?;
+                    // NOTE: the data is checked above
+                    Ok(::std::cell::RefMut::map(data, |data| {
+                        Self::try_from_bytes_mut(data).unwrap()
+                    }))
+                }
+
+                /// Copies out the account owned by the program, the account data
+                /// doesn't need to be aligned.
+                pub fn try_read_account_info(info: &#account_info) -> Result<Self, #account_error> {
+                    use #anchor_interface::{Account, PodAccount};
+                    Self::check_owner(info.owner.to_bytes())?;
+                    Self::try_read(&info.data.try_borrow().map_err(#borrow_failed)?)
+                }
+            }
+        } else {
+            quote! {
+                /// Decodes the account owned by the program, ignoring the trailing bytes.
+                pub fn try_from_account_info(info: &#account_info) -> Result<Self, #account_error> {
+                    use #anchor_interface::{Account, AccountDeserialize};
+                    Self::check_owner(info.owner.to_bytes())?;
+                    Self::try_deserialize(&mut &info.data.try_borrow().map_err(#borrow_failed)?[..])
+                }
+            }
+        };
+        self.paths.cfg_solana_program_gen(
+            &[&self.paths.solana_account_info],
+            quote! {
+                impl #name {
+                    #loaders
+                }
+            },
+        )
+    }
+
+    /// Tests of the account (de)serialization for the `gen_tests` module.
+    pub fn gen_account_tests(&self) -> TokenStream {
+        let CratePaths {
+            anchor_interface,
+            bytemuck,
+            ..
+        } = &self.paths;
+        let accounts = &self.idl.accounts;
+        if accounts.is_empty() {
+            return quote!();
+        }
+        let is_zero_copy = |name| {
+            self.typedef_opts
+                .get(name)
+                .is_some_and(|opts| opts.zero_copy)
+        };
+        let layouts = TypeLayouts::host(&self.idl.types);
+        let names: Vec<_> = accounts.iter().map(|acc| item_gen(&acc.name)).collect();
+        let account_info_paths = [&self.paths.solana_account_info, &self.paths.solana_pubkey];
+        let tests = accounts.iter().zip(&names).map(|(acc, name)| {
+            let account_info_test_name = format_ident!("{}_account_info", acc.name.to_snake_case());
+            if is_zero_copy(name) {
+                let test_name = format_ident!("{}_init_from_bytes", acc.name.to_snake_case());
+                let account_info_test = self.paths.cfg_solana_program_gen(
+                    &account_info_paths,
+                    quote! {
+                        #[test]
+                        fn #account_info_test_name() {
+                            let mut buffer = Vec::new();
+                            let data = aligned_account_data::<#name>(&mut buffer);
+                            #name::try_init_bytes(data).unwrap();
+                            load_account_info(data, #name::OWNER, |info| #name::load(info).map(drop));
+                            load_account_info(data, #name::OWNER, |info| {
+                                #name::load_mut(info).map(drop)
+                            });
+                            load_account_info(data, #name::OWNER, |info| {
+                                let read = #name::try_read_account_info(info)?;
+                                assert_eq!(read.to_account_data(), **info.data.borrow());
+                                Ok(())
+                            });
+                        }
+                    },
+                );
+                quote! {
+                    #[test]
+                    fn #test_name() {
+                        init_from_bytes::<#name>();
+                    }
+
+                    #account_info_test
+                }
+            } else {
+                let test_name = format_ident!("{}_serialize_deserialize", acc.name.to_snake_case());
+                // the zeroed account followed by a trailing byte
+                let data_len = layouts
+                    .borsh_fixed_size(&IdlType::Defined {
+                        name: acc.name.clone(),
+                        generics: vec![],
+                    })
+                    .map_or(UNKNOWN_SIZE_TEST_DATA_LEN, |size| {
+                        acc.discriminator.len() + size + 1
+                    });
+                let account_info_test = self.paths.cfg_solana_program_gen(
+                    &account_info_paths,
+                    quote! {
+                        #[test]
+                        fn #account_info_test_name() {
+                            let mut data = zeroed_account_data::<#name>(#data_len);
+                            load_account_info(&mut data, #name::OWNER, |info| {
+                                #name::try_from_account_info(info).map(drop)
+                            });
+                        }
+                    },
+                );
+                quote! {
+                    #[test]
+                    fn #test_name() {
+                        serialize_deserialize::<#name>(#data_len);
+                    }
+
+                    #account_info_test
+                }
+            }
+        });
+        let serialize_deserialize = if names.iter().any(|name| !is_zero_copy(name)) {
+            quote! {
+                /// `data_len` zeroed bytes starting with the account discriminator.
+                fn zeroed_account_data<T: #anchor_interface::Account>(data_len: usize) -> Vec<u8> {
+                    let mut data = vec![0; data_len];
+                    data[..T::DISCRIMINATOR.len()].copy_from_slice(T::DISCRIMINATOR);
+                    data
+                }
+
+                /// Deserializes the account from `data_len` zeroed bytes and checks that it's
+                /// serialized back into the same data, and the decoding variants
+                /// handle the trailing and missing data.
+                fn serialize_deserialize<T>(data_len: usize)
+                where
+                    T: #anchor_interface::AccountSerialize + #anchor_interface::AccountDeserialize,
+                {
+                    let mut data = zeroed_account_data::<T>(data_len);
+                    let account = T::try_deserialize(&mut data.as_slice()).unwrap();
+                    let mut serialized = Vec::new();
+                    account.try_serialize(&mut serialized).unwrap();
+                    assert_eq!(serialized, data[..serialized.len()]);
+                    let account = T::try_deserialize(&mut serialized.as_slice()).unwrap();
+                    let mut reserialized = Vec::new();
+                    account.try_serialize(&mut reserialized).unwrap();
+                    assert_eq!(reserialized, serialized);
+
+                    let mut rest = data.as_slice();
+                    T::try_deserialize_stream(&mut rest).unwrap();
+                    assert_eq!(rest.len(), data.len() - serialized.len());
+                    T::try_deserialize_strict(&data).unwrap();
+                    data[serialized.len()] = 1;
+                    assert!(T::try_deserialize_strict(&data).is_err());
+                    let account = T::try_deserialize_lenient(T::DISCRIMINATOR).unwrap();
+                    let mut lenient = Vec::new();
+                    account.try_serialize(&mut lenient).unwrap();
+                    assert_eq!(lenient, serialized);
+                    // an oversized length prefix at any offset of the zeroed account
+                    // fails at the end of the data
+                    for offset in T::DISCRIMINATOR.len()..=serialized.len() {
+                        let mut data = serialized[..offset].to_vec();
+                        data.extend([0xff, 0xff, 0xff, 0x0f]);
+                        let _ = T::try_deserialize_lenient(&data);
+                    }
+                }
+            }
+        } else {
+            quote!()
+        };
+        let init_from_bytes = if names.iter().any(is_zero_copy) {
+            quote! {
+                /// Zeroed account data in the `buffer`, the account following
+                /// the discriminator is aligned.
+                fn aligned_account_data<T: #anchor_interface::PodAccount>(
+                    buffer: &mut Vec<u128>,
+                ) -> &mut [u8] {
+                    let len = T::DISCRIMINATOR.len() + ::core::mem::size_of::<T>();
+                    let align = ::core::mem::align_of::<T>();
+                    let offset = (align - T::DISCRIMINATOR.len() % align) % align;
+                    *buffer = vec![0u128; (offset + len).div_ceil(16)];
+                    &mut #bytemuck::cast_slice_mut::<u128, u8>(buffer)[offset..offset + len]
+                }
+
+                /// Initializes the account in zeroed data and checks that it's
+                /// read back in place and copied out into the same data.
+                fn init_from_bytes<T>()
+                where
+                    T: #anchor_interface::PodAccount + #anchor_interface::AccountSerialize,
+                {
+                    let mut buffer = Vec::new();
+                    let data = aligned_account_data::<T>(&mut buffer);
+                    let len = data.len();
+                    let account: *const T = T::try_init_bytes(data).unwrap();
+                    assert_eq!(&data[..T::DISCRIMINATOR.len()], T::DISCRIMINATOR);
+                    assert!(::core::ptr::eq(T::try_from_bytes(data).unwrap(), account));
+                    assert!(::core::ptr::eq(T::try_from_bytes_mut(data).unwrap(), account));
+                    assert!(T::try_init_bytes(data).is_err());
+                    assert!(::core::ptr::eq(T::try_init_bytes_forced(data).unwrap(), account));
+
+                    let read = T::try_read(data).unwrap();
+                    assert_eq!(read.to_account_data(), data);
+                    let mut serialized = Vec::new();
+                    read.try_serialize(&mut serialized).unwrap();
+                    assert_eq!(serialized, data);
+                    // reads the account out of the unaligned data
+                    let mut unaligned = vec![0; len + 1];
+                    unaligned[1..].copy_from_slice(data);
+                    assert_eq!(T::try_read(&unaligned[1..]).unwrap().to_account_data(), data);
+                }
+            }
+        } else {
+            quote!()
+        };
+        let accounts_len = accounts.len();
+        let pubkey = self.paths.pubkey();
+        let account_info = self.paths.account_info();
+        let load_account_info = self.paths.cfg_solana_program_gen(
+            &account_info_paths,
+            quote! {
+                /// Checks that `load` accepts the account data owned by `program_owner` only.
+                fn load_account_info(
+                    data: &mut [u8],
+                    program_owner: [u8; 32],
+                    load: impl Fn(&#account_info) -> Result<(), #anchor_interface::errors::AccountError>,
+                ) {
+                    let (key, mut lamports) = (#pubkey::default(), 0);
+                    let program_owner = #pubkey::new_from_array(program_owner);
+                    for (owner, loaded) in [(program_owner, true), (#pubkey::new_unique(), false)] {
+                        let info = #account_info::new(&key, false, true, &mut lamports, data, &owner, false, 0);
+                        assert_eq!(load(&info).is_ok(), loaded);
+                    }
+                }
+            },
+        );
+        quote! {
+            use super::super::state::*;
+            #[allow(unused_imports)]
+            use #anchor_interface::{Account, PodAccount};
+
+            #load_account_info
+
+            #serialize_deserialize
+            #init_from_bytes
+
+            #(#tests)*
+
+            #[test]
+            fn discriminators_are_unique() {
+                let unique: ::std::collections::BTreeSet<&[u8]> =
+                    [#(#names::DISCRIMINATOR),*].into_iter().collect();
+                assert_eq!(unique.len(), #accounts_len);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::{generator, idl, legacy_idl, ADDRESS};
+    use crate::{Generator, GeneratorOptions};
+
+    const CONFIG: &str = r#"{
+        "name": "Config",
+        "type": { "kind": "struct", "fields": [{ "name": "flag", "type": "bool" }] }
+    }"#;
+
+    fn gen_accounts(opts: GeneratorOptions) -> String {
+        generator(opts, &legacy_idl("", CONFIG))
+            .gen_accounts()
+            .to_string()
+    }
+
+    #[test]
+    fn owner_without_address() {
+        assert!(gen_accounts(Default::default()).contains("compile_error"));
+    }
+
+    #[test]
+    fn owner_of_program_id() {
+        let opts = GeneratorOptions::builder().program_id(ADDRESS).build();
+        let accounts = gen_accounts(opts);
+        assert!(!accounts.contains("compile_error"));
+        assert!(accounts.contains("const OWNER"));
+    }
+
+    #[test]
+    fn borsh_errors() {
+        let accounts = gen_accounts(GeneratorOptions::builder().program_id(ADDRESS).build());
+        // `try_serialize`, `try_deserialize_stream` and `try_deserialize_lenient`
+        let borsh_err = "map_err (:: anchor_interface :: errors :: AccountError :: Borsh)";
+        assert_eq!(accounts.matches(borsh_err).count(), 3);
+    }
+
+    #[test]
+    fn invalid_program_id() {
+        let opts = GeneratorOptions::builder().program_id("invalid").build();
+        assert!(Generator::with_idl_str(opts, &legacy_idl("", CONFIG)).is_err());
+    }
+
+    #[test]
+    fn account_info_loaders_of_configured_paths() {
+        let opts = GeneratorOptions::builder()
+            .program_id(ADDRESS)
+            .solana_pubkey("::solana_pubkey")
+            .solana_account_info("::solana_account_info")
+            .build();
+        let gen = generator(opts, &legacy_idl("", CONFIG));
+        let accounts = gen.gen_accounts().to_string();
+        assert!(accounts.contains(
+            "pub fn try_from_account_info (info : & :: solana_account_info :: AccountInfo)"
+        ));
+        let tests = gen.gen_account_tests().to_string();
+        assert!(tests.contains(":: solana_pubkey :: Pubkey :: new_from_array (program_owner)"));
+        assert!(tests.contains(":: solana_account_info :: AccountInfo :: new"));
+        // not gated by the `solana-program` feature of `anchor-interface`
+        for code in [accounts, tests] {
+            assert!(!code.contains("__private :: solana_program"));
+            assert!(!code.contains("__cfg_solana_program"));
+        }
+    }
+
+    #[test]
+    fn account_info_loaders_of_default_paths() {
+        let opts = GeneratorOptions::builder().program_id(ADDRESS).build();
+        let accounts = gen_accounts(opts);
+        assert!(accounts.contains(":: anchor_interface :: __cfg_solana_program ! { impl Config {"));
+    }
+
+    #[test]
+    fn lenient_padding_of_unknown_size() {
+        let accounts = r#"
+            { "name": "Config", "discriminator": [1, 1, 1, 1, 1, 1, 1, 1] },
+            { "name": "Registry", "discriminator": [2, 2, 2, 2, 2, 2, 2, 2] }
+        "#;
+        let types = r#"
+            {
+                "name": "Config",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "amount", "type": "u64" },
+                        { "name": "label", "type": "string" }
+                    ]
+                }
+            },
+            {
+                "name": "Registry",
+                "type": {
+                    "kind": "struct",
+                    "fields": [{ "name": "items", "type": { "array": ["u8", { "generic": "LEN" }] } }]
+                }
+            }
+        "#;
+        let accounts = generator(Default::default(), &idl("", accounts, types))
+            .gen_accounts()
+            .to_string();
+        // the zeroed `Config` size with the length prefix of the empty `label`
+        assert!(accounts.contains("zero_padded (rest , 12u64)"));
+        // the max account data size
+        assert!(accounts.contains(
+            "zero_padded (rest , :: anchor_interface :: account :: MAX_PERMITTED_DATA_LENGTH)"
+        ));
+    }
 }
@@ -0,0 +1,110 @@
+use std::{env, io, path::PathBuf};
+
+use crate::{Generator, GeneratorOptions};
+
+#[derive(Debug, thiserror::Error)]
+pub enum GenerateError {
+    #[error("env var `{0}`: {1}")]
+    Env(&'static str, env::VarError),
+    #[error("`{}`: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("invalid IDL: {0}")]
+    Idl(String),
+    #[error("invalid IDL account: {0}")]
+    IdlAccount(String),
+    #[error("serialization `{0}` not supported")]
+    UnsupportedSerialization(String),
+    #[error("invalid name `{0}`, expected an identifier")]
+    InvalidName(String),
+    #[error("`out_dir` option not set")]
+    OutDirNotSet,
+    #[error("invalid program id `{0}`")]
+    InvalidProgramId(String),
+    #[error("invalid known account `{name}` address `{address}`")]
+    InvalidKnownAccount { name: String, address: String },
+    #[error("type `{0}` not found in IDL")]
+    UnknownType(String),
+    #[error("instruction `{0}` not found in IDL")]
+    UnknownInstruction(String),
+    #[error("account `{0}` not found in IDL")]
+    UnknownAccount(String),
+    #[error("field `{0}` not found in IDL")]
+    UnknownField(String),
+    #[error("invalid field type key `{0}`, expected `Type::field` or `instruction::arg`")]
+    InvalidFieldTypeKey(String),
+    #[error("account `{0}` can't be an extern type")]
+    ExternAccount(String),
+    #[error("instruction `{instruction}` builder has `{name}` twice, rename the account or arg")]
+    BuilderNameClash { instruction: String, name: String },
+    #[error("instruction `{instruction}` has the field `{name}` twice, rename the arg")]
+    InstructionFieldClash { instruction: String, name: String },
+}
+
+/// Generates the program module from a build script.
+///
+/// The IDL path is relative to `CARGO_MANIFEST_DIR`, the module is written to
+/// `$OUT_DIR/<out_mod>.rs` (`_gen_.rs` by default) and its path is returned.
+/// `out_dir` option is ignored.
+///
+/// ```ignore
+/// // build.rs
+/// fn main() {
+///     let opts = GeneratorOptions::builder().idl("idl.json").build();
+///     anchor_interface_syn::build(opts).unwrap();
+/// }
+///
+/// // lib.rs
+/// include!(concat!(env!("OUT_DIR"), "/_gen_.rs"));
+/// ```
+pub fn build(opt: GeneratorOptions) -> Result<PathBuf, GenerateError> {
+    let var = |name| env::var(name).map_err(|err| GenerateError::Env(name, err));
+    let manifest_dir = var("CARGO_MANIFEST_DIR")?;
+    let out_dir = PathBuf::from(var("OUT_DIR")?);
+
+    let out_file = out_dir.join(format!("{}.rs", opt.out_mod.as_deref().unwrap_or("_gen_")));
+
+    let gen = Generator::try_new(opt, manifest_dir)?;
+    gen.rerun_if_changed();
+    gen.write_program_file(&out_file)
+        .map_err(|source| GenerateError::Io {
+            path: out_file.clone(),
+            source,
+        })?;
+
+    Ok(out_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use darling::util::PathList;
+
+    use super::*;
+    use crate::test_util::{generator, idl};
+
+    #[test]
+    fn path_in_name_list() {
+        // e.g. `program!(zero_copy(a::b), ..)` parsed by `darling`
+        let path: syn::Path = syn::parse_quote!(a::b);
+        let opts = GeneratorOptions {
+            zero_copy: Some(PathList::new(vec![path])),
+            ..Default::default()
+        };
+        let err = Generator::with_idl_str(opts, &idl("", "", ""))
+            .err()
+            .unwrap();
+        assert!(matches!(err, GenerateError::InvalidName(name) if name == "a::b"));
+    }
+
+    #[test]
+    fn program_file_without_out_dir() {
+        let gen = generator(Default::default(), &idl("", "", ""));
+        assert!(matches!(
+            gen.gen_program_file(),
+            Err(GenerateError::OutDirNotSet)
+        ));
+    }
+}
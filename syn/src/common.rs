@@ -1,14 +1,16 @@
 #![allow(dead_code)]
 
+use std::collections::BTreeMap;
+
 use anchor_lang_idl::types::{
     IdlArrayLen, IdlDefinedFields, IdlEnumVariant, IdlField, IdlRepr, IdlSerialization, IdlType,
     IdlTypeDef, IdlTypeDefTy,
 };
 use heck::{ToSnakeCase, ToUpperCamelCase};
-use proc_macro2::{Ident, TokenStream};
+use proc_macro2::{Ident, Literal, TokenStream};
 use quote::{format_ident, quote};
 
-use crate::TypeDefOpts;
+use crate::{CratePaths, ExternType, TypeDefOpts};
 
 #[derive(Copy, Clone, Debug, Default)]
 pub struct FieldListProperties {
@@ -27,7 +29,13 @@ impl FieldListProperties {
         opts: &TypeDefOpts,
         serialization: &IdlSerialization,
         repr: &Option<IdlRepr>,
+        paths: &CratePaths,
     ) -> (Vec<TokenStream>, Vec<TokenStream>) {
+        let CratePaths {
+            borsh, bytemuck, ..
+        } = paths;
+        let mut with_borsh = false;
+        let mut with_bytemuck = false;
         let derive = {
             let mut vec = vec![];
             vec.push(quote!(Clone));
@@ -39,16 +47,18 @@ impl FieldListProperties {
             }
             if !opts.zero_copy {
                 if opts.with_borsh || matches!(serialization, IdlSerialization::Borsh) {
-                    vec.push(quote!(::borsh::BorshDeserialize));
-                    vec.push(quote!(::borsh::BorshSerialize));
+                    vec.push(quote!(#borsh::BorshDeserialize));
+                    vec.push(quote!(#borsh::BorshSerialize));
+                    with_borsh = true;
                 }
                 match serialization {
                     IdlSerialization::Borsh => {
                         // NOTE: derives already added
                     }
                     IdlSerialization::Bytemuck => {
-                        vec.push(quote!(::bytemuck::Pod));
-                        vec.push(quote!(::bytemuck::Zeroable));
+                        vec.push(quote!(#bytemuck::Pod));
+                        vec.push(quote!(#bytemuck::Zeroable));
+                        with_bytemuck = true;
                     }
                     IdlSerialization::BytemuckUnsafe => {
                         // NOTE: must be implemented manually
@@ -60,12 +70,14 @@ impl FieldListProperties {
                 }
             } else {
                 if opts.with_borsh {
-                    vec.push(quote!(::borsh::BorshDeserialize));
-                    vec.push(quote!(::borsh::BorshSerialize));
+                    vec.push(quote!(#borsh::BorshDeserialize));
+                    vec.push(quote!(#borsh::BorshSerialize));
+                    with_borsh = true;
                 }
                 if !matches!(serialization, IdlSerialization::BytemuckUnsafe) {
-                    vec.push(quote!(::bytemuck::Pod));
-                    vec.push(quote!(::bytemuck::Zeroable));
+                    vec.push(quote!(#bytemuck::Pod));
+                    vec.push(quote!(#bytemuck::Zeroable));
+                    with_bytemuck = true;
                 }
             }
             vec
@@ -73,6 +85,13 @@ impl FieldListProperties {
         let attrs = {
             let mut vec = vec![];
 
+            if with_borsh {
+                vec.push(paths.borsh_crate_attr());
+            }
+            if with_bytemuck {
+                vec.push(paths.bytemuck_crate_attr());
+            }
+
             if opts.zero_copy || opts.packed {
                 if opts.zero_copy {
                     vec.push(quote!(#[repr(C)]))
@@ -118,11 +137,16 @@ impl FieldListProperties {
 
 pub fn get_def_field_list_properties(
     defs: &[IdlTypeDef],
+    extern_types: &BTreeMap<Ident, ExternType>,
     fields: &Option<IdlDefinedFields>,
 ) -> FieldListProperties {
     match fields {
-        Some(IdlDefinedFields::Named(fields)) => get_field_list_properties(defs, fields),
-        Some(IdlDefinedFields::Tuple(fields)) => get_type_list_properties(defs, fields),
+        Some(IdlDefinedFields::Named(fields)) => {
+            get_field_list_properties(defs, extern_types, fields)
+        }
+        Some(IdlDefinedFields::Tuple(fields)) => {
+            get_type_list_properties(defs, extern_types, fields)
+        }
         None => FieldListProperties {
             can_copy: true,
             can_derive_default: true,
@@ -130,21 +154,30 @@ pub fn get_def_field_list_properties(
     }
 }
 
-pub fn get_field_list_properties(defs: &[IdlTypeDef], fields: &[IdlField]) -> FieldListProperties {
+pub fn get_field_list_properties(
+    defs: &[IdlTypeDef],
+    extern_types: &BTreeMap<Ident, ExternType>,
+    fields: &[IdlField],
+) -> FieldListProperties {
     get_type_list_properties(
         defs,
+        extern_types,
         &fields.iter().map(|f| f.ty.clone()).collect::<Vec<_>>(),
     )
 }
 
-pub fn get_type_list_properties(defs: &[IdlTypeDef], types: &[IdlType]) -> FieldListProperties {
+pub fn get_type_list_properties(
+    defs: &[IdlTypeDef],
+    extern_types: &BTreeMap<Ident, ExternType>,
+    types: &[IdlType],
+) -> FieldListProperties {
     types.iter().fold(
         FieldListProperties {
             can_copy: true,
             can_derive_default: true,
         },
         |acc, el| {
-            let inner_props = get_type_properties(defs, el);
+            let inner_props = get_type_properties(defs, extern_types, el);
             let can_copy = acc.can_copy && inner_props.can_copy;
             let can_derive_default = acc.can_derive_default && inner_props.can_derive_default;
             FieldListProperties {
@@ -157,6 +190,7 @@ pub fn get_type_list_properties(defs: &[IdlTypeDef], types: &[IdlType]) -> Field
 
 pub fn get_variant_list_properties(
     defs: &[IdlTypeDef],
+    extern_types: &BTreeMap<Ident, ExternType>,
     variants: &[IdlEnumVariant],
 ) -> FieldListProperties {
     variants.iter().fold(
@@ -166,8 +200,12 @@ pub fn get_variant_list_properties(
         },
         |acc, el| {
             let props = match &el.fields {
-                Some(IdlDefinedFields::Named(fields)) => get_field_list_properties(defs, fields),
-                Some(IdlDefinedFields::Tuple(fields)) => get_type_list_properties(defs, fields),
+                Some(IdlDefinedFields::Named(fields)) => {
+                    get_field_list_properties(defs, extern_types, fields)
+                }
+                Some(IdlDefinedFields::Tuple(fields)) => {
+                    get_type_list_properties(defs, extern_types, fields)
+                }
                 None => acc,
             };
             FieldListProperties {
@@ -178,7 +216,11 @@ pub fn get_variant_list_properties(
     )
 }
 
-pub fn get_type_properties(defs: &[IdlTypeDef], ty: &IdlType) -> FieldListProperties {
+pub fn get_type_properties(
+    defs: &[IdlTypeDef],
+    extern_types: &BTreeMap<Ident, ExternType>,
+    ty: &IdlType,
+) -> FieldListProperties {
     match ty {
         IdlType::Bool
         | IdlType::U8
@@ -208,18 +250,26 @@ pub fn get_type_properties(defs: &[IdlTypeDef], ty: &IdlType) -> FieldListProper
             can_derive_default: true,
         },
         IdlType::Defined { name, generics: _ } => {
+            if let Some(extern_type) = extern_types.get(&item_gen(name)) {
+                return FieldListProperties {
+                    can_copy: extern_type.copy || extern_type.pod,
+                    can_derive_default: extern_type.default,
+                };
+            }
             let def = defs.iter().find(|def| &def.name == name).unwrap();
             match &def.ty {
-                IdlTypeDefTy::Struct { fields } => get_def_field_list_properties(defs, fields),
+                IdlTypeDefTy::Struct { fields } => {
+                    get_def_field_list_properties(defs, extern_types, fields)
+                }
                 IdlTypeDefTy::Enum { variants } => {
-                    get_variant_list_properties(defs, variants).whitout_default()
+                    get_variant_list_properties(defs, extern_types, variants).whitout_default()
                 }
-                IdlTypeDefTy::Type { alias } => get_type_properties(defs, alias),
+                IdlTypeDefTy::Type { alias } => get_type_properties(defs, extern_types, alias),
             }
         }
-        IdlType::Option(inner) => get_type_properties(defs, inner),
+        IdlType::Option(inner) => get_type_properties(defs, extern_types, inner),
         IdlType::Array(ty, len) => {
-            let ty = get_type_properties(defs, ty);
+            let ty = get_type_properties(defs, extern_types, ty);
             let can_derive_array_len = match len {
                 IdlArrayLen::Generic(_) => false,
                 IdlArrayLen::Value(len) => *len <= 32,
@@ -235,15 +285,56 @@ pub fn get_type_properties(defs: &[IdlTypeDef], ty: &IdlType) -> FieldListProper
     }
 }
 
+/// `#[cfg(feature = "...")]` if the feature is set.
+pub fn cfg_feature_gen(feature: Option<&str>) -> TokenStream {
+    match feature {
+        Some(feature) => quote!(#[cfg(feature = #feature)]),
+        None => quote!(),
+    }
+}
+
 pub fn docs_gen(docs: &[String]) -> TokenStream {
     quote!(#(#[doc = concat!(" ", #docs)])*)
 }
 
+/// Docs paragraph with the instruction/account/event discriminator.
+pub fn discriminator_docs_gen(discriminator: &[u8]) -> TokenStream {
+    let doc = format!(" Discriminator: `{discriminator:?}`.");
+    quote! {
+        #[doc = " "]
+        #[doc = #doc]
+    }
+}
+
+pub fn pubkey_bytes(address: &str) -> Option<[u8; 32]> {
+    bs58::decode(address).into_vec().ok()?.try_into().ok()
+}
+
+pub fn pubkey_gen(address: &str, paths: &CratePaths) -> Option<TokenStream> {
+    pubkey_of_type_gen(address, &paths.pubkey())
+}
+
+/// `Pubkey` of the given type (path) from the base58 address.
+pub fn pubkey_of_type_gen(address: &str, pubkey: &TokenStream) -> Option<TokenStream> {
+    let bytes = pubkey_array_gen(address)?;
+    Some(quote!(#pubkey::new_from_array(#bytes)))
+}
+
+/// `[u8; 32]` of the base58 address.
+pub fn pubkey_array_gen(address: &str) -> Option<TokenStream> {
+    // NOTE: escaped manually, `Literal::byte_string` output differs
+    // between proc-macro and standalone (CLI) runs
+    let bytes: Literal = format!("b\"{}\"", pubkey_bytes(address)?.escape_ascii())
+        .parse()
+        .unwrap();
+    Some(quote!(*#bytes))
+}
+
 pub fn item_gen(name: &str) -> Ident {
     format_ident!("{}", name.to_upper_camel_case())
 }
 
-pub fn type_gen(ty: &IdlType, opts: &TypeDefOpts) -> TokenStream {
+pub fn type_gen(ty: &IdlType, opts: &TypeDefOpts, paths: &CratePaths) -> TokenStream {
     match ty {
         IdlType::Bool => {
             if opts.zero_copy {
@@ -268,22 +359,29 @@ pub fn type_gen(ty: &IdlType, opts: &TypeDefOpts) -> TokenStream {
         IdlType::I256 => quote!(i256),
         IdlType::Bytes => quote!(Vec<u8>),
         IdlType::String => quote!(String),
-        IdlType::Pubkey => quote!(::solana_program::pubkey::Pubkey),
+        IdlType::Pubkey => paths.pubkey(),
         IdlType::Option(inner) => {
-            let inner = type_gen(inner, opts);
+            let inner = type_gen(inner, opts, paths);
             quote!(Option<#inner>)
         }
         IdlType::Vec(inner) => {
-            let inner = type_gen(inner, opts);
+            let inner = type_gen(inner, opts, paths);
             quote!(Vec<#inner>)
         }
         IdlType::Array(ty, size) => {
             let size = array_type_size(ty, size);
-            let ty = type_gen(ty, opts);
+            let ty = type_gen(ty, opts, paths);
             quote!([#ty; #size])
         }
         IdlType::Defined { name, generics } => {
+            if opts.zero_copy && opts.unit_enums.contains(name) {
+                return quote!(u8);
+            }
             let name = item_gen(name);
+            if let Some(extern_type) = paths.extern_types.get(&name) {
+                let path = &extern_type.path;
+                return quote!(#path);
+            }
             let generics = if generics.is_empty() {
                 quote!()
             } else {
@@ -300,8 +398,8 @@ pub fn type_gen(ty: &IdlType, opts: &TypeDefOpts) -> TokenStream {
     }
 }
 
-pub fn types_gen(types: &[IdlType], opts: &TypeDefOpts) -> TokenStream {
-    let types = types.iter().map(|ty| type_gen(ty, opts));
+pub fn types_gen(types: &[IdlType], opts: &TypeDefOpts, paths: &CratePaths) -> TokenStream {
+    let types = types.iter().map(|ty| type_gen(ty, opts, paths));
     quote!(#(#types),*)
 }
 
@@ -322,19 +420,24 @@ pub struct Field {
     pub ty: TokenStream,
 }
 impl Field {
-    pub fn named(field: &IdlField, opts: &TypeDefOpts) -> Self {
+    pub fn named(field: &IdlField, opts: &TypeDefOpts, paths: &CratePaths) -> Self {
+        let name = field.name.to_snake_case();
+        let ty = match opts.field_types.get(&name) {
+            Some(ty) => quote!(#ty),
+            None => type_gen(&field.ty, opts, paths),
+        };
         Field {
             docs: docs_gen(&field.docs),
-            ident: Some(format_ident!("{}", field.name.to_snake_case())),
-            ty: type_gen(&field.ty, opts),
+            ident: Some(format_ident!("{name}")),
+            ty,
         }
     }
 
-    pub fn tuple(ty: &IdlType, docs: &[String], opts: &TypeDefOpts) -> Self {
+    pub fn tuple(ty: &IdlType, docs: &[String], opts: &TypeDefOpts, paths: &CratePaths) -> Self {
         Field {
             docs: docs_gen(docs),
             ident: None,
-            ty: type_gen(ty, opts),
+            ty: type_gen(ty, opts, paths),
         }
     }
 
@@ -356,44 +459,56 @@ impl Field {
     }
 }
 
-pub fn fields_decl_gen(fields: &[IdlField], opts: &TypeDefOpts) -> TokenStream {
+pub fn fields_decl_gen(fields: &[IdlField], opts: &TypeDefOpts, paths: &CratePaths) -> TokenStream {
     let fields = fields.iter().map(|field| {
-        let field = Field::named(field, opts);
+        let field = Field::named(field, opts, paths);
         field.decl_gen()
     });
     quote!(#(#fields),*)
 }
-pub fn pub_fields_decl_gen(fields: &[IdlField], opts: &TypeDefOpts) -> TokenStream {
+pub fn pub_fields_decl_gen(
+    fields: &[IdlField],
+    opts: &TypeDefOpts,
+    paths: &CratePaths,
+) -> TokenStream {
     let fields = fields.iter().map(|field| {
-        let field = Field::named(field, opts);
+        let field = Field::named(field, opts, paths);
         field.pub_decl_gen()
     });
     quote!(#(#fields),*)
 }
 
-pub fn tuple_decl_gen(types: &[IdlType], opts: &TypeDefOpts) -> TokenStream {
+pub fn tuple_decl_gen(types: &[IdlType], opts: &TypeDefOpts, paths: &CratePaths) -> TokenStream {
     let fields = types.iter().map(|ty| {
-        let field = Field::tuple(ty, &[], opts);
+        let field = Field::tuple(ty, &[], opts, paths);
         field.decl_gen()
     });
     quote!(#(#fields),*)
 }
-pub fn pub_tuple_decl_gen(types: &[IdlType], opts: &TypeDefOpts) -> TokenStream {
+pub fn pub_tuple_decl_gen(
+    types: &[IdlType],
+    opts: &TypeDefOpts,
+    paths: &CratePaths,
+) -> TokenStream {
     let fields = types.iter().map(|ty| {
-        let field = Field::tuple(ty, &[], opts);
+        let field = Field::tuple(ty, &[], opts, paths);
         field.pub_decl_gen()
     });
     quote!(#(#fields),*)
 }
 
-pub fn def_fields_decl_gen(fields: &Option<IdlDefinedFields>, opts: &TypeDefOpts) -> TokenStream {
+pub fn def_fields_decl_gen(
+    fields: &Option<IdlDefinedFields>,
+    opts: &TypeDefOpts,
+    paths: &CratePaths,
+) -> TokenStream {
     match fields {
         Some(IdlDefinedFields::Named(fields)) => {
-            let fields = fields_decl_gen(fields, opts);
+            let fields = fields_decl_gen(fields, opts, paths);
             quote!({ #fields })
         }
         Some(IdlDefinedFields::Tuple(types)) => {
-            let types = tuple_decl_gen(types, opts);
+            let types = tuple_decl_gen(types, opts, paths);
             quote!(( #types );)
         }
         None => quote!(()),
@@ -402,14 +517,15 @@ pub fn def_fields_decl_gen(fields: &Option<IdlDefinedFields>, opts: &TypeDefOpts
 pub fn pub_def_fields_decl_gen(
     fields: &Option<IdlDefinedFields>,
     opts: &TypeDefOpts,
+    paths: &CratePaths,
 ) -> TokenStream {
     match fields {
         Some(IdlDefinedFields::Named(fields)) => {
-            let fields = pub_fields_decl_gen(fields, opts);
+            let fields = pub_fields_decl_gen(fields, opts, paths);
             quote!({ #fields })
         }
         Some(IdlDefinedFields::Tuple(types)) => {
-            let types = pub_tuple_decl_gen(types, opts);
+            let types = pub_tuple_decl_gen(types, opts, paths);
             quote!(( #types );)
         }
         None => quote!(();),
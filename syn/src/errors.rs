@@ -2,7 +2,7 @@ use heck::{ToTitleCase, ToUpperCamelCase};
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 
-use crate::{common::item_gen, Generator};
+use crate::{common::item_gen, CratePaths, Generator};
 
 impl Generator {
     pub fn gen_errors(&self) -> TokenStream {
@@ -23,12 +23,24 @@ impl Generator {
             }
         });
 
+        let CratePaths {
+            solana_program_error,
+            solana_decode_error,
+            num_derive,
+            thiserror,
+            ..
+        } = &self.paths;
+        let (num_traits_import, num_traits_crate_attr) = self.paths.num_traits_crate_attr();
+
         quote! {
-            use ::num_derive::FromPrimitive;
-            use ::thiserror::Error;
-            use ::solana_program::{decode_error::DecodeError, program_error::ProgramError};
+            use #num_derive::FromPrimitive;
+            use #thiserror::Error;
+            use #solana_decode_error::DecodeError;
+            use #solana_program_error::ProgramError;
+            #num_traits_import
 
             #[derive(Error, Clone, Copy, Debug, FromPrimitive, PartialEq, Eq)]
+            #num_traits_crate_attr
             #[repr(u32)]
             pub enum #error_name {
                 #(#errors,)*
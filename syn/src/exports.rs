@@ -5,8 +5,9 @@ use crate::Generator;
 
 impl Generator {
     pub fn gen_exports(&self) -> TokenStream {
+        let anchor_interface = &self.paths.anchor_interface;
         quote! {
-            pub use anchor_interface::prelude::*;
+            pub use #anchor_interface::prelude::*;
         }
     }
 }
@@ -0,0 +1,147 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anchor_lang_idl::types::{
+    Idl, IdlDefinedFields, IdlGenericArg, IdlType, IdlTypeDef, IdlTypeDefTy,
+};
+use darling::util::PathList;
+use heck::ToSnakeCase;
+use proc_macro2::Ident;
+
+use crate::common::item_gen;
+use crate::{path_to_string, ExternType, GenerateError, GeneratorOptions};
+
+/// Applies `only_*`/`skip_*` options and prunes the types unreachable
+/// from the kept instructions and accounts.
+///
+/// Returns `true` if some instructions were filtered out.
+pub fn filter_idl(
+    idl: &mut Idl,
+    opt: &GeneratorOptions,
+    extern_types: &BTreeMap<Ident, ExternType>,
+) -> Result<bool, GenerateError> {
+    let instructions_filtered = retain_items(
+        &mut idl.instructions,
+        |ix| ix.name.to_snake_case(),
+        |name| name.to_snake_case(),
+        opt.only_instructions.as_ref(),
+        opt.skip_instructions.as_ref(),
+        GenerateError::UnknownInstruction,
+    )?;
+    retain_items(
+        &mut idl.accounts,
+        |acc| item_gen(&acc.name).to_string(),
+        |name| item_gen(name).to_string(),
+        opt.only_accounts.as_ref(),
+        opt.skip_accounts.as_ref(),
+        GenerateError::UnknownAccount,
+    )?;
+
+    let filtered = [
+        &opt.only_instructions,
+        &opt.skip_instructions,
+        &opt.only_accounts,
+        &opt.skip_accounts,
+    ]
+    .iter()
+    .any(|list| list.is_some());
+    if filtered {
+        prune_types(idl, extern_types);
+    }
+
+    Ok(instructions_filtered)
+}
+
+/// Keeps the `only` items (all if not set) except the `skip` ones,
+/// returns `true` if some items were removed.
+fn retain_items<T>(
+    items: &mut Vec<T>,
+    item_name: impl Fn(&T) -> String,
+    normalize: impl Fn(&str) -> String,
+    only: Option<&PathList>,
+    skip: Option<&PathList>,
+    unknown: impl Fn(String) -> GenerateError,
+) -> Result<bool, GenerateError> {
+    let names = |list: Option<&PathList>| {
+        list.map(|list| {
+            list.iter()
+                .map(|path| {
+                    let name = path_to_string(path);
+                    let normalized = normalize(&name);
+                    if items.iter().any(|item| item_name(item) == normalized) {
+                        Ok(normalized)
+                    } else {
+                        Err(unknown(name))
+                    }
+                })
+                .collect::<Result<BTreeSet<_>, _>>()
+        })
+        .transpose()
+    };
+    let only = names(only)?;
+    let skip = names(skip)?.unwrap_or_default();
+
+    let len = items.len();
+    items.retain(|item| {
+        let name = item_name(item);
+        only.as_ref().is_none_or(|only| only.contains(&name)) && !skip.contains(&name)
+    });
+    Ok(items.len() != len)
+}
+
+/// Removes the types unreachable from the instruction args and accounts.
+fn prune_types(idl: &mut Idl, extern_types: &BTreeMap<Ident, ExternType>) {
+    let mut pending: Vec<&str> = idl.accounts.iter().map(|acc| acc.name.as_str()).collect();
+    idl.instructions
+        .iter()
+        .flat_map(|ix| &ix.args)
+        .for_each(|arg| defined_names(&arg.ty, &mut pending));
+
+    let mut reachable = BTreeSet::new();
+    while let Some(name) = pending.pop() {
+        if !reachable.insert(name.to_string()) || extern_types.contains_key(&item_gen(name)) {
+            continue;
+        }
+        if let Some(def) = idl.types.iter().find(|def| def.name == name) {
+            typedef_defined_names(def, &mut pending);
+        }
+    }
+
+    idl.types.retain(|ty| reachable.contains(&ty.name));
+}
+
+pub(crate) fn typedef_defined_names<'a>(def: &'a IdlTypeDef, out: &mut Vec<&'a str>) {
+    let fields_defined_names =
+        |fields: &'a Option<IdlDefinedFields>, out: &mut Vec<&'a str>| match fields {
+            Some(IdlDefinedFields::Named(fields)) => fields
+                .iter()
+                .for_each(|field| defined_names(&field.ty, out)),
+            Some(IdlDefinedFields::Tuple(types)) => {
+                types.iter().for_each(|ty| defined_names(ty, out))
+            }
+            None => {}
+        };
+    match &def.ty {
+        IdlTypeDefTy::Struct { fields } => fields_defined_names(fields, out),
+        IdlTypeDefTy::Enum { variants } => variants
+            .iter()
+            .for_each(|var| fields_defined_names(&var.fields, out)),
+        IdlTypeDefTy::Type { alias } => defined_names(alias, out),
+    }
+}
+
+fn defined_names<'a>(ty: &'a IdlType, out: &mut Vec<&'a str>) {
+    match ty {
+        IdlType::Defined { name, generics } => {
+            out.push(name);
+            generics.iter().for_each(|arg| {
+                if let IdlGenericArg::Type { ty } = arg {
+                    defined_names(ty, out);
+                }
+            });
+        }
+        IdlType::Option(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+            defined_names(inner, out)
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,243 @@
+use anchor_lang_idl::types::{IdlDefinedFields, IdlField, IdlType, IdlTypeDef, IdlTypeDefTy};
+use heck::ToSnakeCase;
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+
+use crate::common::{array_type_size, cfg_feature_gen, item_gen};
+use crate::{CratePaths, Generator, TypeDefOpts};
+
+impl Generator {
+    pub fn gen_fuzz(&self) -> TokenStream {
+        if !self.fuzz {
+            return quote!();
+        }
+        let anchor_interface = &self.paths.anchor_interface;
+        let instruction_cfg = cfg_feature_gen(self.features.instruction.as_deref());
+        let state_cfg = cfg_feature_gen(self.features.state.as_deref());
+        let master_enum_name = self.master_enum_name();
+        let accounts = self.idl.accounts.iter().map(|acc| {
+            let name = item_gen(&acc.name);
+            let zero_copy = self
+                .typedef_opts
+                .get(&name)
+                .is_some_and(|opts| opts.zero_copy);
+            if zero_copy {
+                quote! {
+                    #state_cfg
+                    let _ = <super::state::#name as #anchor_interface::PodAccount>
+                        ::try_from_bytes(data);
+                }
+            } else {
+                quote! {
+                    #state_cfg
+                    let _ = <super::state::#name as #anchor_interface::AccountDeserialize>
+                        ::try_deserialize_strict(data);
+                    #state_cfg
+                    let _ = <super::state::#name as #anchor_interface::AccountDeserialize>
+                        ::try_deserialize_lenient(data);
+                }
+            }
+        });
+        quote! {
+            /// Fuzzing entry point feeding `data` to the instruction and account parsers,
+            /// which must return an error on invalid data and never panic.
+            pub fn parse(data: &[u8]) {
+                #instruction_cfg
+                let _ = super::instruction::#master_enum_name::unpack(data);
+                #(#accounts)*
+            }
+        }
+    }
+
+    /// Tests of the `parse` fuzzing entry point for the `gen_tests` module.
+    pub fn gen_fuzz_tests(&self) -> TokenStream {
+        if !self.fuzz {
+            return quote!();
+        }
+        let discriminators = self
+            .idl
+            .instructions
+            .iter()
+            .map(|ix| &ix.discriminator)
+            .chain(self.idl.accounts.iter().map(|acc| &acc.discriminator))
+            .map(|discriminator| quote!(&[#(#discriminator),*]));
+        quote! {
+            use super::super::fuzz::parse;
+
+            /// Max size of the account data the prefixes are tested in.
+            const PREFIX_OFFSETS: usize = 256;
+
+            #[test]
+            fn parse_empty_and_short() {
+                parse(&[]);
+                for len in 1..=12 {
+                    parse(&vec![0; len]);
+                    parse(&vec![0xff; len]);
+                }
+            }
+
+            /// Parses the data of every discriminator with the max and oversized
+            /// length prefixes at every offset of the zeroed data.
+            #[test]
+            fn parse_length_prefixes() {
+                let discriminators: &[&[u8]] = &[#(#discriminators),*];
+                for discriminator in discriminators {
+                    parse(discriminator);
+                    for offset in 0..=PREFIX_OFFSETS {
+                        for prefix in [[0xff, 0xff, 0xff, 0xff], [0xff, 0xff, 0xff, 0x0f]] {
+                            let mut data = discriminator.to_vec();
+                            data.resize(data.len() + offset, 0);
+                            data.extend(prefix);
+                            parse(&data);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// `Arbitrary` implementation of the struct or enum, if `fuzz` is set.
+    pub fn arbitrary_typedef_gen(&self, ty: &IdlTypeDef, opts: &TypeDefOpts) -> TokenStream {
+        if !self.fuzz {
+            return quote!();
+        }
+        let paths = &self.paths;
+        let body = match &ty.ty {
+            IdlTypeDefTy::Struct { fields: None } => quote!(Self()),
+            IdlTypeDefTy::Struct { fields } => {
+                let fields = arbitrary_def_fields_gen(fields, opts, paths);
+                quote!(Self #fields)
+            }
+            IdlTypeDefTy::Enum { variants } => {
+                let last = variants.len().saturating_sub(1) as u32;
+                let variants = variants.iter().enumerate().map(|(idx, var)| {
+                    let idx = idx as u32;
+                    let name = item_gen(&var.name);
+                    let fields = arbitrary_def_fields_gen(&var.fields, opts, paths);
+                    quote!(#idx => Self::#name #fields,)
+                });
+                quote! {
+                    match u.int_in_range(0..=#last)? {
+                        #(#variants)*
+                        _ => unreachable!(),
+                    }
+                }
+            }
+            IdlTypeDefTy::Type { .. } => return quote!(),
+        };
+        let cfg = cfg_feature_gen(self.features.fuzz.as_deref());
+        arbitrary_impl_gen(&item_gen(&ty.name), body, &cfg, paths)
+    }
+}
+
+pub fn arbitrary_impl_gen(
+    name: &Ident,
+    body: TokenStream,
+    cfg: &TokenStream,
+    paths: &CratePaths,
+) -> TokenStream {
+    let arbitrary = &paths.arbitrary;
+    quote! {
+        #cfg
+        impl<'a> #arbitrary::Arbitrary<'a> for #name {
+            fn arbitrary(u: &mut #arbitrary::Unstructured<'a>) -> #arbitrary::Result<Self> {
+                Ok(#body)
+            }
+        }
+    }
+}
+
+fn has_pubkey(ty: &IdlType) -> bool {
+    match ty {
+        IdlType::Pubkey => true,
+        IdlType::Option(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+            has_pubkey(inner)
+        }
+        _ => false,
+    }
+}
+
+/// Arbitrary value of the type taken from `u: &mut Unstructured`.
+///
+/// NOTE: `Pubkey` doesn't implement `Arbitrary`, so it's built from the bytes.
+pub fn arbitrary_gen(ty: &IdlType, paths: &CratePaths) -> TokenStream {
+    let arbitrary = &paths.arbitrary;
+    if !has_pubkey(ty) {
+        return quote!(#arbitrary::Arbitrary::arbitrary(u)?);
+    }
+    match ty {
+        IdlType::Option(inner) => {
+            let inner = arbitrary_gen(inner, paths);
+            quote! {
+                if #arbitrary::Arbitrary::arbitrary(u)? {
+                    Some(#inner)
+                } else {
+                    None
+                }
+            }
+        }
+        IdlType::Vec(inner) => {
+            let inner = arbitrary_gen(inner, paths);
+            quote! {
+                (0..u.arbitrary_len::<[u8; 32]>()?)
+                    .map(|_| Ok(#inner))
+                    .collect::<#arbitrary::Result<Vec<_>>>()?
+            }
+        }
+        IdlType::Array(inner, size) => {
+            let size = array_type_size(inner, size);
+            let inner = arbitrary_gen(inner, paths);
+            quote! {
+                (0..#size)
+                    .map(|_| Ok(#inner))
+                    .collect::<#arbitrary::Result<Vec<_>>>()?
+                    .try_into()
+                    .unwrap_or_else(|_: Vec<_>| unreachable!())
+            }
+        }
+        _ => {
+            let pubkey = paths.pubkey();
+            quote!(#pubkey::new_from_array(#arbitrary::Arbitrary::arbitrary(u)?))
+        }
+    }
+}
+
+/// `name: value` of the named fields.
+pub fn arbitrary_fields_gen(
+    fields: &[IdlField],
+    opts: &TypeDefOpts,
+    paths: &CratePaths,
+) -> Vec<TokenStream> {
+    let arbitrary = &paths.arbitrary;
+    fields
+        .iter()
+        .map(|field| {
+            let name = field.name.to_snake_case();
+            let value = if opts.field_types.contains_key(&name) {
+                quote!(#arbitrary::Arbitrary::arbitrary(u)?)
+            } else {
+                arbitrary_gen(&field.ty, paths)
+            };
+            let name = format_ident!("{name}");
+            quote!(#name: #value)
+        })
+        .collect()
+}
+
+fn arbitrary_def_fields_gen(
+    fields: &Option<IdlDefinedFields>,
+    opts: &TypeDefOpts,
+    paths: &CratePaths,
+) -> TokenStream {
+    match fields {
+        Some(IdlDefinedFields::Named(fields)) => {
+            let fields = arbitrary_fields_gen(fields, opts, paths);
+            quote!({ #(#fields),* })
+        }
+        Some(IdlDefinedFields::Tuple(types)) => {
+            let values = types.iter().map(|ty| arbitrary_gen(ty, paths));
+            quote!(( #(#values),* ))
+        }
+        None => quote!(),
+    }
+}
@@ -0,0 +1,166 @@
+use std::io::Read;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::read::ZlibDecoder;
+use serde_json::Value;
+
+use crate::GenerateError;
+
+/// IDL account header: discriminator, authority and data length.
+const IDL_ACCOUNT_HEADER_LEN: usize = 8 + 32 + 4;
+
+/// Returns IDL JSON from the IDL file, IDL account data
+/// or `solana account --output json` dump of the IDL account.
+///
+/// The data starting with `{` or `[` is JSON, its syntax errors have the line and column.
+pub fn idl_json(data: &[u8]) -> Result<Vec<u8>, GenerateError> {
+    if !matches!(
+        data.iter().find(|byte| !byte.is_ascii_whitespace()),
+        Some(b'{' | b'[')
+    ) {
+        return decode_idl_account(data);
+    }
+    let value =
+        serde_json::from_slice::<Value>(data).map_err(|err| GenerateError::Idl(err.to_string()))?;
+    match value.pointer("/account/data") {
+        Some(account_data) => {
+            decode_idl_account(&account_json_data(account_data).map_err(GenerateError::IdlAccount)?)
+        }
+        None => Ok(data.to_vec()),
+    }
+}
+
+/// Whether the IDL JSON is of the legacy spec (pre Anchor v0.30), which has no
+/// `metadata.spec` (the same check as of [`anchor_lang_idl::convert::convert_idl`]).
+pub fn is_legacy(json: &[u8]) -> bool {
+    let Ok(idl) = serde_json::from_slice::<Value>(json) else {
+        return false;
+    };
+    idl.pointer("/metadata/spec")
+        .and_then(Value::as_str)
+        .is_none()
+}
+
+/// Sets the empty `metadata.address` of the legacy IDL JSON without one,
+/// so it's converted and the address is set with the `program_id` option.
+pub fn with_legacy_address(json: Vec<u8>) -> Vec<u8> {
+    let Ok(Value::Object(mut idl)) = serde_json::from_slice::<Value>(&json) else {
+        return json;
+    };
+    // NOTE: the new IDLs have the top-level address
+    if idl.contains_key("address") {
+        return json;
+    }
+    let Value::Object(metadata) = idl
+        .entry("metadata")
+        .or_insert_with(|| Value::Object(Default::default()))
+    else {
+        return json;
+    };
+    if metadata.contains_key("address") {
+        return json;
+    }
+    metadata.insert("address".to_string(), Value::String(String::new()));
+    serde_json::to_vec(&idl).unwrap_or(json)
+}
+
+/// Returns account data from the raw account dump
+/// or `solana account --output json` output.
+pub fn account_dump_data(data: &[u8]) -> Result<Vec<u8>, String> {
+    let Ok(value) = serde_json::from_slice::<Value>(data) else {
+        return Ok(data.to_vec());
+    };
+    match value.pointer("/account/data") {
+        Some(account_data) => account_json_data(account_data),
+        None => Err("JSON without `account.data`".to_string()),
+    }
+}
+
+/// Decompresses IDL JSON from the Anchor IDL account data.
+pub fn decode_idl_account(data: &[u8]) -> Result<Vec<u8>, GenerateError> {
+    let header = data.get(..IDL_ACCOUNT_HEADER_LEN).ok_or_else(|| {
+        GenerateError::IdlAccount(format!(
+            "{} bytes, expected at least {IDL_ACCOUNT_HEADER_LEN}",
+            data.len()
+        ))
+    })?;
+    let len = u32::from_le_bytes(header[40..].try_into().unwrap()) as usize;
+    let compressed = data
+        .get(IDL_ACCOUNT_HEADER_LEN..IDL_ACCOUNT_HEADER_LEN + len)
+        .ok_or_else(|| {
+            GenerateError::IdlAccount(format!(
+                "data length {len} exceeds {} available bytes",
+                data.len() - IDL_ACCOUNT_HEADER_LEN
+            ))
+        })?;
+
+    let mut json = Vec::new();
+    ZlibDecoder::new(compressed)
+        .read_to_end(&mut json)
+        .map_err(|err| GenerateError::IdlAccount(format!("decompress: {err}")))?;
+    Ok(json)
+}
+
+/// Account data from `"data": ["<base64>", "base64"]` of `solana account --output json`.
+fn account_json_data(value: &Value) -> Result<Vec<u8>, String> {
+    let invalid = || format!("invalid account data `{value}`");
+    let (data, encoding) = match value.as_array().map(Vec::as_slice) {
+        Some([Value::String(data), Value::String(encoding)]) => (data, encoding),
+        _ => return Err(invalid()),
+    };
+    if encoding != "base64" {
+        return Err(format!(
+            "account data encoding `{encoding}` not supported, use `base64`"
+        ));
+    }
+    STANDARD.decode(data).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::ZlibEncoder, Compression};
+
+    use super::*;
+
+    const IDL: &[u8] = br#"{ "address": "11111111111111111111111111111111" }"#;
+
+    /// IDL account data with the compressed `json`.
+    fn idl_account(json: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(json).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut data = vec![0; IDL_ACCOUNT_HEADER_LEN - 4];
+        data.extend((compressed.len() as u32).to_le_bytes());
+        data.extend(compressed);
+        data
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(idl_json(IDL).unwrap(), IDL);
+        let indented = [b"\n  ", IDL].concat();
+        assert_eq!(idl_json(&indented).unwrap(), indented);
+    }
+
+    #[test]
+    fn invalid_json_position() {
+        let err = idl_json(b"{\n  \"address\": 1,\n}")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("line 3 column 1"), "{err}");
+    }
+
+    #[test]
+    fn idl_account_data() {
+        assert_eq!(idl_json(&idl_account(IDL)).unwrap(), IDL);
+    }
+
+    #[test]
+    fn idl_account_json_dump() {
+        let data = STANDARD.encode(idl_account(IDL));
+        let dump = format!(r#"{{ "account": {{ "data": ["{data}", "base64"] }} }}"#);
+        assert_eq!(idl_json(dump.as_bytes()).unwrap(), IDL);
+    }
+}
@@ -1,36 +1,437 @@
-use anchor_lang_idl::types::{IdlInstruction, IdlInstructionAccount, IdlInstructionAccountItem};
+use std::collections::{BTreeMap, BTreeSet};
+
+use anchor_lang_idl::types::{
+    IdlInstruction, IdlInstructionAccount, IdlInstructionAccountItem, IdlSeed, IdlType,
+};
 use heck::{ToShoutySnakeCase, ToSnakeCase, ToTitleCase, ToUpperCamelCase};
 use proc_macro2::{Ident, TokenStream};
 use quote::{format_ident, quote};
 
-use crate::common::{docs_gen, item_gen, Field};
-use crate::Generator;
+use crate::common::{
+    cfg_feature_gen, discriminator_docs_gen, docs_gen, item_gen, pubkey_gen, Field,
+};
+use crate::fuzz::{arbitrary_fields_gen, arbitrary_gen, arbitrary_impl_gen};
+use crate::{CratePaths, FeatureOptions, GenerateError, Generator, TypeDefOpts};
 
 impl Generator {
-    pub fn gen_instructions(&self) -> TokenStream {
-        let master_enum_name = item_gen(&format!(
+    pub fn master_enum_name(&self) -> Ident {
+        item_gen(&format!(
             "{}Instruction",
             self.idl.metadata.name.to_upper_camel_case()
-        ));
-        let ixs: Vec<_> = self.idl.instructions.iter().map(Instruction::new).collect();
-        let master_enum = master_enum_gen(&master_enum_name, &ixs);
-        let ix_builders_and_parsers = ix_builders_and_parsers_gen(&master_enum_name, &ixs);
+        ))
+    }
+
+    pub fn gen_instructions(&self) -> TokenStream {
+        let master_enum_name = self.master_enum_name();
+        let ixs: Vec<_> = self
+            .idl
+            .instructions
+            .iter()
+            .map(|ix| {
+                let opts = self.ix_opts.get(&ix.name).cloned().unwrap_or_default();
+                Instruction::new(ix, &opts, &self.paths)
+            })
+            .collect();
+        let master_enum = master_enum_gen(
+            &master_enum_name,
+            &ixs,
+            self.unknown_instruction,
+            &self.known_accounts,
+            self.fuzz,
+            &self.features,
+            &self.paths,
+        );
+        let ix_builders_and_parsers = ix_builders_and_parsers_gen(
+            &master_enum_name,
+            &ixs,
+            &self.known_accounts,
+            self.fuzz,
+            &self.features,
+            &self.paths,
+        );
+        let ix_typed_builders = if self.builders {
+            let program_id = self.program_id_gen();
+            let cfg = cfg_feature_gen(self.features.builders.as_deref());
+            ixs.iter()
+                .map(|ix| {
+                    ix_typed_builder_gen(
+                        ix,
+                        program_id.as_ref(),
+                        &self.known_accounts,
+                        &cfg,
+                        &self.paths,
+                    )
+                })
+                .collect()
+        } else {
+            quote!()
+        };
+        let introspection = self.introspection_gen(&master_enum_name);
         quote! {
             #master_enum
             #ix_builders_and_parsers
+            #ix_typed_builders
+            #introspection
+        }
+    }
+
+    /// `SysvarInstruction` loading the program instructions of the current
+    /// transaction from the instructions sysvar.
+    fn introspection_gen(&self, master_enum_name: &Ident) -> TokenStream {
+        if !self.introspection {
+            return quote!();
+        }
+        let paths = &self.paths;
+        let instruction = paths.instruction();
+        let account_info = paths.account_info();
+        let program_error = paths.program_error();
+        let instructions_sysvar = &paths.solana_instructions_sysvar;
+        let program_id = self.program_id_or_error_gen();
+        let cfg = cfg_feature_gen(self.features.introspection.as_deref());
+        let account_indexes_cfg = cfg_feature_gen(self.features.account_indexes.as_deref());
+        quote! {
+            #cfg
+            /// Instruction of the program in the current transaction,
+            /// loaded from the instructions sysvar.
+            #[derive(Debug)]
+            pub struct SysvarInstruction {
+                /// Index of the instruction in the transaction.
+                pub index: usize,
+                pub instruction: #master_enum_name,
+                /// Loaded instruction, [`SysvarInstruction::decode`] names its accounts.
+                pub ix: #instruction,
+            }
+            #cfg
+            impl SysvarInstruction {
+                /// Loads the instruction at `index` of the current transaction,
+                /// `None` for the instructions of other programs.
+                pub fn load(
+                    index: usize,
+                    instructions_sysvar: &#account_info,
+                ) -> Result<Option<Self>, #program_error> {
+                    let ix = #instructions_sysvar::load_instruction_at_checked(index, instructions_sysvar)?;
+                    if ix.program_id != #program_id {
+                        return Ok(None);
+                    }
+                    let instruction = #master_enum_name::unpack(&ix.data)
+                        .map_err(|_| #program_error::InvalidInstructionData)?;
+                    Ok(Some(Self {
+                        index,
+                        instruction,
+                        ix,
+                    }))
+                }
+
+                /// Loads all the instructions of the program in the current transaction,
+                /// skipping the ones not decoded by the interface (e.g. added by
+                /// a newer program version), which fail [`SysvarInstruction::load`].
+                pub fn load_all(instructions_sysvar: &#account_info) -> Result<Vec<Self>, #program_error> {
+                    let mut out = Vec::new();
+                    for index in 0.. {
+                        match Self::load(index, instructions_sysvar) {
+                            Ok(ix) => out.extend(ix),
+                            Err(#program_error::InvalidInstructionData) => {}
+                            // NOTE: the index is out of the transaction instructions
+                            Err(#program_error::InvalidArgument) => break,
+                            Err(err) => return Err(err),
+                        }
+                    }
+                    Ok(out)
+                }
+
+                /// Decodes the instruction with its named accounts, e.g. `ix.decode::<Route>()`,
+                /// fails for the other instructions and the missing accounts.
+                #account_indexes_cfg
+                pub fn decode<T>(&self) -> Result<T, std::io::Error>
+                where
+                    T: for<'a> TryFrom<&'a #instruction, Error = std::io::Error>,
+                {
+                    T::try_from(&self.ix)
+                }
+            }
+        }
+    }
+
+    /// Tests of the instruction enum and account indexes for the `gen_tests` module.
+    pub fn gen_instruction_tests(&self) -> TokenStream {
+        let ixs = &self.idl.instructions;
+        if ixs.is_empty() {
+            return quote!();
+        }
+        let master_enum_name = self.master_enum_name();
+        let account_indexes_cfg = cfg_feature_gen(self.features.account_indexes.as_deref());
+        let discriminators: Vec<_> = ixs
+            .iter()
+            .map(|ix| {
+                let discriminator = &ix.discriminator;
+                quote!(&[#(#discriminator),*])
+            })
+            .collect();
+        let pack_unpack_tests = ixs.iter().zip(&discriminators).map(|(ix, discriminator)| {
+            let test_name = format_ident!("{}_pack_unpack", ix.name.to_snake_case());
+            quote! {
+                #[test]
+                fn #test_name() {
+                    pack_unpack(#discriminator);
+                }
+            }
+        });
+        let account_indexes_tests = ixs.iter().map(|ix| {
+            let test_name = format_ident!("{}_account_indexes", ix.name.to_snake_case());
+            let account_idxs_name = format_ident!("{}AccountIndexes", item_gen(&ix.name));
+            let accounts: Vec<_> = ix.accounts.iter().flat_map(acc_item_flatten).collect();
+            let names: Vec<_> = accounts.iter().map(|acc| acc_name(acc, false)).collect();
+            let upper_names = accounts.iter().map(|acc| acc_name(acc, true));
+            let idx: Vec<_> = (0..accounts.len()).collect();
+            let idx_u8 = (0..accounts.len()).map(|idx| idx as u8);
+            let accounts_helpers = if accounts.is_empty() {
+                quote!()
+            } else {
+                quote! {
+                    assert_eq!(out.accounts.to_vec(), [#(#idx),*]);
+                    let names: Vec<_> = out.accounts.iter().map(|(name, _)| name).collect();
+                    assert_eq!(names, [#(stringify!(#names)),*]);
+                }
+            };
+            quote! {
+                #account_indexes_cfg
+                #[test]
+                fn #test_name() {
+                    let indexes: &[u8] = &[#(#idx_u8),*];
+                    let out = #account_idxs_name::try_from(indexes).unwrap();
+                    #(
+                        assert_eq!(#account_idxs_name::#upper_names, #idx);
+                        assert_eq!(out.accounts.#names, #idx);
+                    )*
+                    #accounts_helpers
+                    assert!(out.trailing_accounts.is_empty());
+                }
+            }
+        });
+        let pubkey = self.paths.pubkey();
+        let account_meta = self.paths.account_meta();
+        let instruction = self.paths.instruction();
+        let from_instruction_tests = ixs.iter().zip(&discriminators).map(|(ix, discriminator)| {
+            let test_name = format_ident!("{}_from_instruction", ix.name.to_snake_case());
+            let name = item_gen(&ix.name);
+            // one trailing account
+            let accounts_len = ix.accounts.iter().flat_map(acc_item_flatten).count() + 1;
+            let index_checks = account_index_checks_gen(ix, quote!(decoded), quote!(keys));
+            quote! {
+                #account_indexes_cfg
+                #[test]
+                fn #test_name() {
+                    let keys: Vec<_> = (0..#accounts_len).map(|_| #pubkey::new_unique()).collect();
+                    let ix = #instruction {
+                        program_id: #pubkey::new_unique(),
+                        accounts: keys.iter().map(|key| #account_meta::new(*key, false)).collect(),
+                        data: unpack_zeroed(#discriminator).pack(),
+                    };
+                    let decoded = #name::try_from(&ix).unwrap();
+                    #index_checks
+                    let out = decoded.into_instruction();
+                    assert_eq!(out.program_id, ix.program_id);
+                    assert_eq!(out.data, ix.data);
+                    let out_keys: Vec<_> = out.accounts.iter().map(|meta| meta.pubkey).collect();
+                    assert_eq!(out_keys, keys);
+                }
+            }
+        });
+        let introspection_tests = self.gen_introspection_tests(&ixs[0]);
+        quote! {
+            use super::super::instruction::*;
+            use super::zeroed_ix_data;
+
+            fn unpack_zeroed(discriminator: &[u8]) -> #master_enum_name {
+                #master_enum_name::unpack(&zeroed_ix_data(discriminator)).unwrap()
+            }
+
+            /// Unpacks the instruction with zeroed args and checks that it's
+            /// packed back into the same data.
+            fn pack_unpack(discriminator: &[u8]) {
+                let ix = unpack_zeroed(discriminator);
+                assert_eq!(ix.discriminator(), discriminator);
+                let packed = ix.pack();
+                assert_eq!(packed[..discriminator.len()], *discriminator);
+                assert!(packed[discriminator.len()..].iter().all(|b| *b == 0));
+                let repacked = #master_enum_name::unpack(&packed).unwrap().pack();
+                assert_eq!(repacked, packed);
+            }
+
+            #(#pack_unpack_tests)*
+
+            #[test]
+            fn unpack_short_data() {
+                for len in 0..8 {
+                    assert!(#master_enum_name::unpack(&vec![0; len]).is_err());
+                }
+            }
+
+            #[test]
+            fn discriminators_are_unique() {
+                let discriminators: &[&[u8]] = &[#(#discriminators),*];
+                let unique: ::std::collections::BTreeSet<_> = discriminators
+                    .iter()
+                    .map(|discriminator| unpack_zeroed(discriminator).discriminator().to_vec())
+                    .collect();
+                assert_eq!(unique.len(), discriminators.len());
+            }
+
+            #(#account_indexes_tests)*
+            #(#from_instruction_tests)*
+            #introspection_tests
+        }
+    }
+
+    /// Tests of `SysvarInstruction` loading the `ix` instructions
+    /// from the instructions sysvar data.
+    fn gen_introspection_tests(&self, ix: &IdlInstruction) -> TokenStream {
+        if !self.introspection {
+            return quote!();
+        }
+        let paths = &self.paths;
+        let pubkey = paths.pubkey();
+        let account_info = paths.account_info();
+        let program_error = paths.program_error();
+        let instructions_sysvar = &paths.solana_instructions_sysvar;
+        let program_id = self.program_id_or_error_gen();
+        let cfg = cfg_feature_gen(self.features.introspection.as_deref());
+        let account_indexes_cfg = cfg_feature_gen(self.features.account_indexes.as_deref());
+        let name = item_gen(&ix.name);
+        let discriminator = &ix.discriminator;
+        let accounts_len = ix.accounts.iter().flat_map(acc_item_flatten).count();
+        let index_checks = account_index_checks_gen(ix, quote!(decoded), quote!(accounts));
+        let missing_account = if accounts_len == 0 {
+            quote!()
+        } else {
+            let missing_len = accounts_len - 1;
+            quote! {
+                let mut ix = ix;
+                ix.ix.accounts.truncate(#missing_len);
+                assert!(ix.decode::<#name>().is_err());
+            }
+        };
+        quote! {
+            /// Loads the instructions from the instructions sysvar data of
+            /// the `(program_id, data)` instructions with the `accounts`.
+            #cfg
+            fn load_sysvar_instructions<T>(
+                ixs: &[(#pubkey, &[u8])],
+                accounts: &[#pubkey],
+                load: impl FnOnce(&#account_info) -> T,
+            ) -> T {
+                use #instructions_sysvar::{
+                    construct_instructions_data, BorrowedAccountMeta, BorrowedInstruction,
+                };
+
+                let ixs: Vec<_> = ixs
+                    .iter()
+                    .map(|(program_id, data)| BorrowedInstruction {
+                        program_id,
+                        accounts: accounts
+                            .iter()
+                            .map(|pubkey| BorrowedAccountMeta {
+                                pubkey,
+                                is_signer: false,
+                                is_writable: true,
+                            })
+                            .collect(),
+                        data,
+                    })
+                    .collect();
+                let mut data = construct_instructions_data(&ixs);
+                let (key, owner, mut lamports) = (#instructions_sysvar::ID, #pubkey::default(), 0);
+                let info = #account_info::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+                load(&info)
+            }
+
+            /// The program instructions are loaded, skipping the other programs
+            /// and the undecodable data.
+            #cfg
+            #[test]
+            fn sysvar_instruction_load() {
+                let program_id = #program_id;
+                let data = zeroed_ix_data(&[#(#discriminator),*]);
+                let accounts = [#pubkey::new_unique()];
+                let ixs = [
+                    (program_id, &data[..]),
+                    (#pubkey::new_unique(), &data[..]),
+                    (program_id, &[0xff; 4][..]),
+                    (program_id, &data[..]),
+                ];
+                load_sysvar_instructions(&ixs, &accounts, |sysvar| {
+                    let ix = SysvarInstruction::load(0, sysvar).unwrap().unwrap();
+                    assert_eq!(ix.index, 0);
+                    assert_eq!(ix.instruction.discriminator(), &[#(#discriminator),*]);
+                    assert_eq!(ix.ix.data, data);
+                    assert_eq!(ix.ix.program_id, program_id);
+                    assert_eq!(ix.ix.accounts[0].pubkey, accounts[0]);
+                    assert!(SysvarInstruction::load(1, sysvar).unwrap().is_none());
+                    assert_eq!(
+                        SysvarInstruction::load(2, sysvar).unwrap_err(),
+                        #program_error::InvalidInstructionData
+                    );
+                    assert_eq!(
+                        SysvarInstruction::load(4, sysvar).unwrap_err(),
+                        #program_error::InvalidArgument
+                    );
+
+                    let indexes: Vec<_> = SysvarInstruction::load_all(sysvar)
+                        .unwrap()
+                        .iter()
+                        .map(|ix| ix.index)
+                        .collect();
+                    assert_eq!(indexes, [0, 3]);
+                });
+            }
+
+            /// The loaded instruction is decoded with the named accounts.
+            #cfg
+            #account_indexes_cfg
+            #[test]
+            fn sysvar_instruction_decode() {
+                let data = zeroed_ix_data(&[#(#discriminator),*]);
+                let accounts: Vec<_> = (0..#accounts_len).map(|_| #pubkey::new_unique()).collect();
+                load_sysvar_instructions(&[(#program_id, &data)], &accounts, |sysvar| {
+                    let ix = SysvarInstruction::load(0, sysvar).unwrap().unwrap();
+                    let decoded: #name = ix.decode().unwrap();
+                    let named: Vec<_> = decoded.accounts.iter().map(|(_, key)| *key).collect();
+                    assert_eq!(named, accounts);
+                    #index_checks
+                    #missing_account
+                });
+            }
         }
     }
 }
 
+/// Checks that the `decoded` instruction accounts are the `keys`
+/// at the `<Instruction>AccountIndexes` constants.
+fn account_index_checks_gen(
+    ix: &IdlInstruction,
+    decoded: TokenStream,
+    keys: TokenStream,
+) -> TokenStream {
+    let account_idxs_name = format_ident!("{}AccountIndexes", item_gen(&ix.name));
+    let accounts: Vec<_> = ix.accounts.iter().flat_map(acc_item_flatten).collect();
+    let names = accounts.iter().map(|acc| acc_name(acc, false));
+    let upper_names = accounts.iter().map(|acc| acc_name(acc, true));
+    quote! {
+        #(assert_eq!(#decoded.accounts.#names, #keys[#account_idxs_name::#upper_names]);)*
+    }
+}
+
 struct Instruction<'a> {
     pub idl: &'a IdlInstruction,
     pub ident: Ident,
     pub args: Vec<Field>,
+    /// `name: value` of the arbitrary args.
+    pub arbitrary_args: Vec<TokenStream>,
 
     pub borsh_deser_ident: Ident,
 }
 impl<'a> Instruction<'a> {
-    pub fn new(ix: &'a IdlInstruction) -> Self {
+    pub fn new(ix: &'a IdlInstruction, opts: &TypeDefOpts, paths: &CratePaths) -> Self {
         let ident = item_gen(&ix.name);
         let borsh_deser_ident = format_ident!("{}Deserializer", ident);
         Self {
@@ -39,62 +440,77 @@ impl<'a> Instruction<'a> {
             args: ix
                 .args
                 .iter()
-                .map(|arg| Field::named(arg, &Default::default()))
+                .map(|arg| Field::named(arg, opts, paths))
                 .collect(),
+            arbitrary_args: arbitrary_fields_gen(&ix.args, opts, paths),
             borsh_deser_ident,
         }
     }
 }
 
-fn master_enum_gen(master_enum_name: &Ident, ixs: &[Instruction<'_>]) -> TokenStream {
-    let ixs_decl = ixs.iter().map(|ix| {
-        let docs = if !ix.idl.docs.is_empty() {
-            docs_gen(&ix.idl.docs)
-        } else {
-            let head = format!(" {}", ix.idl.name.to_title_case());
-            quote!(#[doc = #head])
-        };
-        let acc_docs = acc_docs_gen(&ix.idl.accounts);
-        let name = &ix.ident;
-        let args = if ix.idl.args.is_empty() {
-            quote!()
-        } else {
-            let args = ix.args.iter().map(Field::decl_gen);
-            quote!({ #(#args),* })
-        };
-        quote! {
-            #docs
-            #[doc = " "]
-            #acc_docs
-            #name #args
-        }
-    });
+fn master_enum_gen(
+    master_enum_name: &Ident,
+    ixs: &[Instruction<'_>],
+    unknown: bool,
+    known_accounts: &BTreeMap<String, String>,
+    fuzz: bool,
+    features: &FeatureOptions,
+    paths: &CratePaths,
+) -> TokenStream {
+    let borsh = &paths.borsh;
+    let display_cfg = cfg_feature_gen(features.display.as_deref());
+    let mut ixs_decl: Vec<_> = ixs
+        .iter()
+        .map(|ix| {
+            let docs = ix_docs_gen(ix);
+            let acc_docs = acc_docs_gen(&ix.idl.accounts, known_accounts);
+            let name = &ix.ident;
+            let args = if ix.idl.args.is_empty() {
+                quote!()
+            } else {
+                let args = ix.args.iter().map(Field::decl_gen);
+                quote!({ #(#args),* })
+            };
+            quote! {
+                #docs
+                #[doc = " "]
+                #acc_docs
+                #name #args
+            }
+        })
+        .collect();
 
-    let discriminator_matches = ixs.iter().map(|ix| {
-        let name = &ix.ident;
-        let args = if ix.args.is_empty() {
-            quote!()
-        } else {
-            quote!({ .. })
-        };
-        let discriminator = &ix.idl.discriminator;
-        quote!( Self::#name #args => &[#(#discriminator),*], )
-    });
+    let mut discriminator_matches: Vec<_> = ixs
+        .iter()
+        .map(|ix| {
+            let name = &ix.ident;
+            let args = if ix.args.is_empty() {
+                quote!()
+            } else {
+                quote!({ .. })
+            };
+            let discriminator = &ix.idl.discriminator;
+            quote!( Self::#name #args => &[#(#discriminator),*], )
+        })
+        .collect();
 
-    let borsh_serialize_matches = ixs.iter().map(|ix| {
-        let name = &ix.ident;
-        let (args, serialize_args) = if ix.args.is_empty() {
-            (quote!(), quote!())
-        } else {
-            let args = ix.args.iter().map(|arg| &arg.ident);
-            let serialize_args = ix.args.iter().map(|arg| {
-                let name = &arg.ident;
-                quote!(::borsh::BorshSerialize::serialize(#name, writer)?;)
-            });
-            (quote!({ #(#args),* }), quote!(#(#serialize_args)*))
-        };
-        quote!(Self::#name #args => { #serialize_args })
-    });
+    let mut borsh_serialize_matches: Vec<_> = ixs
+        .iter()
+        .map(|ix| {
+            let name = &ix.ident;
+            let (args, serialize_args) = if ix.args.is_empty() {
+                (quote!(), quote!())
+            } else {
+                let args = ix.args.iter().map(|arg| &arg.ident);
+                let serialize_args = ix.args.iter().map(|arg| {
+                    let name = &arg.ident;
+                    quote!(#borsh::BorshSerialize::serialize(#name, writer)?;)
+                });
+                (quote!({ #(#args),* }), quote!(#(#serialize_args)*))
+            };
+            quote!(Self::#name #args => { #serialize_args })
+        })
+        .collect();
 
     let borsh_deserializers = ixs.iter().map(|ix| -> TokenStream {
         let name = &ix.ident;
@@ -102,10 +518,10 @@ fn master_enum_gen(master_enum_name: &Ident, ixs: &[Instruction<'_>]) -> TokenSt
         let args = ix.args.iter().map(|arg| &arg.ident);
         quote! {
             struct #helper_name ( #master_enum_name );
-            impl ::borsh::de::BorshDeserialize for #helper_name {
+            impl #borsh::de::BorshDeserialize for #helper_name {
                 fn deserialize_reader<R: std::io::prelude::Read>(_reader: &mut R) -> std::io::Result<Self> {
                     Ok(Self ( #master_enum_name::#name {
-                        #(#args: ::borsh::BorshDeserialize::deserialize_reader(_reader)?,)*
+                        #(#args: #borsh::BorshDeserialize::deserialize_reader(_reader)?,)*
                     }))}
             }
             impl From<#helper_name> for #master_enum_name {
@@ -116,6 +532,28 @@ fn master_enum_gen(master_enum_name: &Ident, ixs: &[Instruction<'_>]) -> TokenSt
         }
     });
 
+    let mut display_matches: Vec<_> = ixs
+        .iter()
+        .map(|ix| {
+            let name = &ix.ident;
+            let title = name.to_string();
+            let args = if ix.args.is_empty() {
+                quote!()
+            } else {
+                let args = ix.args.iter().map(|arg| &arg.ident);
+                let bindings = ix.args.iter().map(arg_binding);
+                quote!({ #(#args: #bindings),* })
+            };
+            let acc_display = acc_display_gen(&ix.idl.accounts, false);
+            let args_display = args_display_gen(ix, false);
+            quote!(Self::#name #args => {
+                write!(f, #title)?;
+                #acc_display
+                #args_display
+            })
+        })
+        .collect();
+
     let unpack_matches = ixs.iter().map(|ix| {
         let discriminator = &ix.idl.discriminator;
         let helper_name = &ix.borsh_deser_ident;
@@ -124,13 +562,92 @@ fn master_enum_gen(master_enum_name: &Ident, ixs: &[Instruction<'_>]) -> TokenSt
         })
     });
 
+    let mut arbitrary_variants: Vec<_> = ixs
+        .iter()
+        .map(|ix| {
+            let name = &ix.ident;
+            let args = &ix.arbitrary_args;
+            if args.is_empty() {
+                quote!(Self::#name)
+            } else {
+                quote!(Self::#name { #(#args),* })
+            }
+        })
+        .collect();
+
+    let (discriminator_ty, unpack_unknown) = if unknown {
+        ixs_decl.push(quote! {
+            /// Instruction filtered out of the interface.
+            Unknown {
+                discriminator: [u8; 8],
+                data: Vec<u8>,
+            }
+        });
+        discriminator_matches.push(quote! {
+            Self::Unknown { discriminator, .. } => discriminator,
+        });
+        borsh_serialize_matches.push(quote! {
+            Self::Unknown { data, .. } => {
+                writer.write_all(data)?;
+            }
+        });
+        let arbitrary = &paths.arbitrary;
+        arbitrary_variants.push(quote! {
+            Self::Unknown {
+                discriminator: #arbitrary::Arbitrary::arbitrary(u)?,
+                data: #arbitrary::Arbitrary::arbitrary(u)?,
+            }
+        });
+        display_matches.push(quote! {
+            Self::Unknown { discriminator, data } => {
+                write!(f, "Unknown")?;
+                write!(f, "\n  discriminator: {:?}", discriminator)?;
+                write!(f, "\n  data: {:?}", data)?;
+            }
+        });
+        (
+            quote!(&[u8; 8]),
+            quote! {
+                _ => Self::Unknown {
+                    discriminator: discriminator.try_into().unwrap(),
+                    data: ix_data.to_vec(),
+                },
+            },
+        )
+    } else {
+        (
+            quote!(&'static [u8; 8]),
+            quote! {
+                _ => return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "invalid discriminator",
+                )),
+            },
+        )
+    };
+
+    let arbitrary_impl = if fuzz && !arbitrary_variants.is_empty() {
+        let last = (arbitrary_variants.len() - 1) as u32;
+        let idx = 0..=last;
+        let body = quote! {
+            match u.int_in_range(0..=#last)? {
+                #(#idx => #arbitrary_variants,)*
+                _ => unreachable!(),
+            }
+        };
+        let cfg = cfg_feature_gen(features.fuzz.as_deref());
+        arbitrary_impl_gen(master_enum_name, body, &cfg, paths)
+    } else {
+        quote!()
+    };
+
     quote! {
         #[derive(Debug)]
         pub enum #master_enum_name {
             #(#ixs_decl),*
         }
         impl #master_enum_name {
-            pub fn discriminator(&self) -> &'static [u8; 8] {
+            pub fn discriminator(&self) -> #discriminator_ty {
                 match self {
                     #(#discriminator_matches)*
                 }
@@ -138,31 +655,44 @@ fn master_enum_gen(master_enum_name: &Ident, ixs: &[Instruction<'_>]) -> TokenSt
             pub fn pack(self) -> Vec<u8> {let mut out = Vec::new();
                 out.extend(self.discriminator());
 
-                let data = ::borsh::to_vec(&self).unwrap();
+                let data = #borsh::to_vec(&self).unwrap();
                 out.extend(data);
 
                 out
             }
             pub fn unpack(data: &[u8]) -> ::std::io::Result<Self> {
-                use ::borsh::BorshDeserialize;
+                use #borsh::BorshDeserialize;
 
+                if data.len() < 8 {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "instruction data too short",
+                    ));
+                }
                 let (discriminator, mut ix_data) = data.split_at(8);
 
                 Ok(match discriminator {
                     #(#unpack_matches)*
-                    _ => return Err(std::io::Error::new(
-                        std::io::ErrorKind::InvalidInput,
-                        "invalid discriminator",
-                    )),
+                    #unpack_unknown
                 })
             }
         }
 
-        impl ::borsh::BorshSerialize for #master_enum_name {
-            fn serialize<W: ::borsh::io::Write>(
+        #display_cfg
+        impl ::core::fmt::Display for #master_enum_name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                match self {
+                    #(#display_matches)*
+                }
+                Ok(())
+            }
+        }
+
+        impl #borsh::BorshSerialize for #master_enum_name {
+            fn serialize<W: #borsh::io::Write>(
                 &self,
                 writer: &mut W,
-            ) -> ::core::result::Result<(), ::borsh::io::Error> {
+            ) -> ::core::result::Result<(), #borsh::io::Error> {
                 match self {
                     #(#borsh_serialize_matches)*
                 }
@@ -171,36 +701,141 @@ fn master_enum_gen(master_enum_name: &Ident, ixs: &[Instruction<'_>]) -> TokenSt
         }
 
         #(#borsh_deserializers)*
+
+        #arbitrary_impl
     }
 }
 
-fn acc_docs(acc: &IdlInstructionAccount, idx: &mut usize) -> String {
-    let out = format!(
-        " {}. `[{}{}{}]` {}",
-        idx,
+fn ix_docs_gen(ix: &Instruction<'_>) -> TokenStream {
+    let docs = if !ix.idl.docs.is_empty() {
+        docs_gen(&ix.idl.docs)
+    } else {
+        let head = format!(" {}", ix.idl.name.to_title_case());
+        quote!(#[doc = #head])
+    };
+    let discriminator_docs = discriminator_docs_gen(&ix.idl.discriminator);
+    quote! {
+        #docs
+        #discriminator_docs
+    }
+}
+
+fn acc_markers(acc: &IdlInstructionAccount) -> String {
+    format!(
+        "[{}{}{}]",
         if acc.signer { "signer" } else { "" },
         if acc.signer && acc.writable { ", " } else { "" },
         if acc.writable { "writable" } else { "" },
-        acc.name.to_title_case().to_lowercase(),
+    )
+}
+fn acc_title(acc: &IdlInstructionAccount) -> String {
+    acc.name.to_title_case().to_lowercase()
+}
+
+fn acc_head(acc: &IdlInstructionAccount) -> String {
+    let optional = if acc.optional { " (optional)" } else { "" };
+    format!("`{}` {}{}", acc_markers(acc), acc_title(acc), optional)
+}
+
+fn seed_docs(seed: &IdlSeed) -> String {
+    match seed {
+        IdlSeed::Const(seed) => match std::str::from_utf8(&seed.value) {
+            Ok(value) if !value.is_empty() && value.bytes().all(|b| b.is_ascii_graphic()) => {
+                format!("`b\"{value}\"`")
+            }
+            _ if seed.value.len() == 32 => {
+                format!("`{}`", bs58::encode(&seed.value).into_string())
+            }
+            _ => format!("`{:?}`", seed.value),
+        },
+        IdlSeed::Arg(seed) => format!("arg `{}`", seed.path),
+        IdlSeed::Account(seed) => format!("account `{}`", seed.path),
+    }
+}
+
+/// IDL docs, fixed or known address, PDA and relations of the instruction account.
+fn acc_details(
+    acc: &IdlInstructionAccount,
+    known_accounts: &BTreeMap<String, String>,
+) -> Vec<String> {
+    let mut out = acc.docs.clone();
+    if let Some(address) = &acc.address {
+        out.push(format!("- Address: `{address}`."));
+    } else if let Some(address) = known_accounts.get(&acc.name.to_snake_case()) {
+        out.push(format!(
+            "- Known address: `{address}` (default by the account name, not fixed by the IDL)."
+        ));
+    }
+    if let Some(pda) = &acc.pda {
+        let seeds: Vec<_> = pda.seeds.iter().map(seed_docs).collect();
+        out.push(format!("- PDA seeds: {}.", seeds.join(", ")));
+        if let Some(program) = &pda.program {
+            out.push(format!("- PDA program: {}.", seed_docs(program)));
+        }
+    }
+    if !acc.relations.is_empty() {
+        let relations: Vec<_> = acc.relations.iter().map(|rel| format!("`{rel}`")).collect();
+        out.push(format!("- Relations: {}.", relations.join(", ")));
+    }
+    out
+}
+
+/// Docs of the instruction account struct/builder field.
+fn acc_field_docs_gen(
+    acc: &IdlInstructionAccount,
+    known_accounts: &BTreeMap<String, String>,
+) -> TokenStream {
+    let head = format!(" {}", acc_head(acc));
+    let details = acc_details(acc, known_accounts);
+    if details.is_empty() {
+        quote!(#[doc = #head])
+    } else {
+        let details = details.iter().map(|line| format!(" {line}"));
+        quote! {
+            #[doc = #head]
+            #[doc = " "]
+            #(#[doc = #details])*
+        }
+    }
+}
+
+fn acc_docs(
+    acc: &IdlInstructionAccount,
+    idx: &mut usize,
+    known_accounts: &BTreeMap<String, String>,
+) -> Vec<String> {
+    let mut out = vec![format!(" {}. {}", idx, acc_head(acc))];
+    // indented to continue the list item
+    out.extend(
+        acc_details(acc, known_accounts)
+            .into_iter()
+            .map(|line| format!("     {line}")),
     );
     *idx += 1;
     out
 }
-fn acc_item_docs(acc: &IdlInstructionAccountItem, idx: &mut usize) -> Vec<String> {
+fn acc_item_docs(
+    acc: &IdlInstructionAccountItem,
+    idx: &mut usize,
+    known_accounts: &BTreeMap<String, String>,
+) -> Vec<String> {
     let mut out = Vec::new();
     match acc {
         IdlInstructionAccountItem::Single(acc) => {
-            out.push(acc_docs(acc, idx));
+            out.extend(acc_docs(acc, idx, known_accounts));
         }
         IdlInstructionAccountItem::Composite(accs) => {
             accs.accounts.iter().for_each(|acc| {
-                out.extend(acc_item_docs(acc, idx));
+                out.extend(acc_item_docs(acc, idx, known_accounts));
             });
         }
     }
     out
 }
-fn acc_docs_gen(accounts: &[IdlInstructionAccountItem]) -> TokenStream {
+fn acc_docs_gen(
+    accounts: &[IdlInstructionAccountItem],
+    known_accounts: &BTreeMap<String, String>,
+) -> TokenStream {
     if accounts.is_empty() {
         return quote!();
     }
@@ -208,7 +843,7 @@ fn acc_docs_gen(accounts: &[IdlInstructionAccountItem]) -> TokenStream {
     let mut idx = 0;
     let mut docs = Vec::new();
     accounts.iter().for_each(|acc| {
-        docs.extend(acc_item_docs(acc, &mut idx));
+        docs.extend(acc_item_docs(acc, &mut idx, known_accounts));
     });
     quote! {
         #[doc = " Accounts expected by this instruction:"]
@@ -216,6 +851,84 @@ fn acc_docs_gen(accounts: &[IdlInstructionAccountItem]) -> TokenStream {
     }
 }
 
+fn acc_display(acc: &IdlInstructionAccount, idx: &mut usize, with_keys: bool) -> TokenStream {
+    let out = if with_keys {
+        let name = format_ident!("{}", acc.name.to_snake_case());
+        let line = format!(
+            "\n    {}. {} {}: {{}}",
+            idx,
+            acc_markers(acc),
+            acc_title(acc)
+        );
+        quote!(write!(f, #line, self.accounts.#name)?;)
+    } else {
+        let line = format!("\n    {}. {} {}", idx, acc_markers(acc), acc_title(acc));
+        quote!(write!(f, #line)?;)
+    };
+    *idx += 1;
+    out
+}
+fn acc_item_display(
+    acc: &IdlInstructionAccountItem,
+    idx: &mut usize,
+    with_keys: bool,
+) -> Vec<TokenStream> {
+    let mut out = Vec::new();
+    match acc {
+        IdlInstructionAccountItem::Single(acc) => {
+            out.push(acc_display(acc, idx, with_keys));
+        }
+        IdlInstructionAccountItem::Composite(accs) => {
+            accs.accounts.iter().for_each(|acc| {
+                out.extend(acc_item_display(acc, idx, with_keys));
+            });
+        }
+    }
+    out
+}
+fn acc_display_gen(accounts: &[IdlInstructionAccountItem], with_keys: bool) -> TokenStream {
+    if accounts.is_empty() {
+        return quote!();
+    }
+
+    let mut idx = 0;
+    let mut lines = Vec::new();
+    accounts.iter().for_each(|acc| {
+        lines.extend(acc_item_display(acc, &mut idx, with_keys));
+    });
+    quote! {
+        write!(f, "\n  accounts:")?;
+        #(#lines)*
+    }
+}
+
+fn args_display_gen(ix: &Instruction<'_>, with_self: bool) -> TokenStream {
+    if ix.args.is_empty() {
+        return quote!();
+    }
+
+    let lines = ix.args.iter().map(|arg| {
+        let name = arg.ident.as_ref().unwrap();
+        let line = format!("\n    {name}: {{:?}}");
+        if with_self {
+            quote!(write!(f, #line, self.#name)?;)
+        } else {
+            let binding = arg_binding(arg);
+            quote!(write!(f, #line, #binding)?;)
+        }
+    });
+    quote! {
+        write!(f, "\n  args:")?;
+        #(#lines)*
+    }
+}
+
+/// Match binding of the instruction arg, prefixed so an arg named
+/// like a generated local (e.g. `f` of `Display`) doesn't shadow it.
+pub(crate) fn arg_binding(arg: &Field) -> Ident {
+    format_ident!("arg_{}", arg.ident.as_ref().unwrap())
+}
+
 fn acc_name(acc: &IdlInstructionAccount, upper: bool) -> TokenStream {
     let name = format_ident!(
         "{}",
@@ -227,7 +940,7 @@ fn acc_name(acc: &IdlInstructionAccount, upper: bool) -> TokenStream {
     );
     quote!(#name)
 }
-fn acc_item_name(acc: &IdlInstructionAccountItem, upper: bool) -> Vec<TokenStream> {
+pub(crate) fn acc_item_name(acc: &IdlInstructionAccountItem, upper: bool) -> Vec<TokenStream> {
     let mut out = Vec::new();
     match acc {
         IdlInstructionAccountItem::Single(acc) => out.push(acc_name(acc, upper)),
@@ -240,29 +953,160 @@ fn acc_item_name(acc: &IdlInstructionAccountItem, upper: bool) -> Vec<TokenStrea
     out
 }
 
-fn acc_meta(acc: &IdlInstructionAccount) -> TokenStream {
+fn acc_item_flatten(acc: &IdlInstructionAccountItem) -> Vec<&IdlInstructionAccount> {
+    let mut out = Vec::new();
+    match acc {
+        IdlInstructionAccountItem::Single(acc) => out.push(acc),
+        IdlInstructionAccountItem::Composite(accs) => {
+            accs.accounts
+                .iter()
+                .for_each(|acc| out.extend(acc_item_flatten(acc)));
+        }
+    }
+    out
+}
+
+fn acc_address(
+    acc: &IdlInstructionAccount,
+    known_accounts: &BTreeMap<String, String>,
+    paths: &CratePaths,
+) -> Option<TokenStream> {
+    match &acc.address {
+        Some(address) => pubkey_gen(address, paths),
+        None => known_accounts
+            .get(&acc.name.to_snake_case())
+            .and_then(|address| pubkey_gen(address, paths)),
+    }
+}
+
+fn acc_meta(acc: &IdlInstructionAccount, paths: &CratePaths) -> TokenStream {
+    let account_meta = paths.account_meta();
     let name = format_ident!("{}", acc.name.to_snake_case());
     let is_signer = acc.signer;
     let new = format_ident!("{}", if acc.writable { "new" } else { "new_readonly" });
-    quote!(::solana_program::instruction::AccountMeta::#new(#name, #is_signer))
+    quote!(#account_meta::#new(#name, #is_signer))
 }
-fn acc_item_meta(acc: &IdlInstructionAccountItem) -> Vec<TokenStream> {
+fn acc_item_meta(acc: &IdlInstructionAccountItem, paths: &CratePaths) -> Vec<TokenStream> {
     let mut out = Vec::new();
     match acc {
-        IdlInstructionAccountItem::Single(acc) => out.push(acc_meta(acc)),
+        IdlInstructionAccountItem::Single(acc) => out.push(acc_meta(acc, paths)),
         IdlInstructionAccountItem::Composite(accs) => {
             accs.accounts
                 .iter()
-                .for_each(|acc| out.extend(acc_item_meta(acc)));
+                .for_each(|acc| out.extend(acc_item_meta(acc, paths)));
         }
     }
     out
 }
 
-fn ix_builders_and_parsers_gen(master_enum_name: &Ident, ixs: &[Instruction<'_>]) -> TokenStream {
+/// Generic `<Instruction>Accounts<T>` with a `T` per instruction account.
+fn ix_accounts_gen(ix: &Instruction<'_>, known_accounts: &BTreeMap<String, String>) -> TokenStream {
+    let name = &ix.ident;
+    let accounts_name = format_ident!("{}Accounts", name);
+    let accounts: Vec<_> = ix.idl.accounts.iter().flat_map(acc_item_flatten).collect();
+    let names: Vec<_> = accounts.iter().map(|acc| acc_name(acc, false)).collect();
+    let name_strs = accounts.iter().map(|acc| acc.name.to_snake_case());
+    let docs = accounts
+        .iter()
+        .map(|acc| acc_field_docs_gen(acc, known_accounts));
+    let len = accounts.len();
+    let struct_docs = format!(" Accounts of [`{name}`] in the IDL order.");
+    // NOTE: `T` is used by the marker only if the instruction has no accounts
+    let (marker_decl, marker, f) = if accounts.is_empty() {
+        (
+            quote!(#[doc(hidden)] pub _marker: ::core::marker::PhantomData<T>,),
+            quote!(_marker: ::core::marker::PhantomData,),
+            quote!(_f),
+        )
+    } else {
+        (quote!(), quote!(), quote!(mut f))
+    };
+    let from_slice = if accounts.is_empty() {
+        quote!(Some((#accounts_name { #marker }, values)))
+    } else {
+        quote! {
+            match values {
+                [#(#names,)* rest @ ..] => Some((#accounts_name { #(#names,)* }, rest)),
+                _ => None,
+            }
+        }
+    };
+    quote! {
+        #[doc = #struct_docs]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+        pub struct #accounts_name<T> {
+            #(
+                #docs
+                pub #names: T,
+            )*
+            #marker_decl
+        }
+        impl<T> #accounts_name<T> {
+            /// Number of the accounts.
+            pub const LEN: usize = #len;
+            /// Names of the accounts.
+            pub const NAMES: [&'static str; #len] = [#(#name_strs),*];
+
+            /// Maps every account value with `f`.
+            pub fn map<U>(self, #f: impl FnMut(T) -> U) -> #accounts_name<U> {
+                #accounts_name {
+                    #(#names: f(self.#names),)*
+                    #marker
+                }
+            }
+
+            /// Borrows every account value.
+            pub fn as_ref(&self) -> #accounts_name<&T> {
+                #accounts_name {
+                    #(#names: &self.#names,)*
+                    #marker
+                }
+            }
+
+            /// Iterates over the account `(name, value)` pairs.
+            pub fn iter(&self) -> impl Iterator<Item = (&'static str, &T)> {
+                let values: [&T; #len] = [#(&self.#names),*];
+                Self::NAMES.into_iter().zip(values)
+            }
+
+            /// Account values in the IDL order.
+            pub fn to_vec(&self) -> Vec<T>
+            where
+                T: Clone,
+            {
+                vec![#(self.#names.clone()),*]
+            }
+
+            /// Borrows the accounts from the head of `values`, returns them with the rest
+            /// of `values`, `None` if `values` are shorter than the accounts.
+            pub fn from_slice(values: &[T]) -> Option<(#accounts_name<&T>, &[T])> {
+                #from_slice
+            }
+        }
+    }
+}
+
+fn ix_builders_and_parsers_gen(
+    master_enum_name: &Ident,
+    ixs: &[Instruction<'_>],
+    known_accounts: &BTreeMap<String, String>,
+    fuzz: bool,
+    features: &FeatureOptions,
+    paths: &CratePaths,
+) -> TokenStream {
+    let pubkey = paths.pubkey();
+    let account_meta = paths.account_meta();
+    let instruction = paths.instruction();
+    let display_cfg = cfg_feature_gen(features.display.as_deref());
+    let account_indexes_cfg = cfg_feature_gen(features.account_indexes.as_deref());
+    let anchor_interface = &paths.anchor_interface;
+    let arbitrary_cfg = cfg_feature_gen(features.fuzz.as_deref());
+    let arbitrary = &paths.arbitrary;
+    let arbitrary_pubkey = arbitrary_gen(&IdlType::Pubkey, paths);
     ixs.iter()
         .map(|ix| {
             let name = &ix.ident;
+            let accounts_name = format_ident!("{}Accounts", name);
             let accounts: Vec<TokenStream> = ix
                 .idl
                 .accounts
@@ -275,38 +1119,100 @@ fn ix_builders_and_parsers_gen(master_enum_name: &Ident, ixs: &[Instruction<'_>]
                 .iter()
                 .flat_map(|acc| acc_item_name(acc, true))
                 .collect();
-            let accounts_decl = accounts.clone();
+            let accounts_struct = ix_accounts_gen(ix, known_accounts);
+            let docs = ix_docs_gen(ix);
             let account_metas: Vec<TokenStream> =
-                ix.idl.accounts.iter().flat_map(acc_item_meta).collect();
+                ix.idl.accounts.iter().flat_map(|acc| acc_item_meta(acc, paths)).collect();
             let params_decl = ix.args.iter().map(Field::pub_decl_gen);
             let params = ix.args.iter().map(|arg| &arg.ident);
             let ix_args = params.clone();
             let account_idxs_name = format_ident!("{}AccountIndexes", name);
-            let try_acc_idx = accounts.clone();
-            let try_acc_idx_iter_mut = if accounts.is_empty() {
+            let try_acc_idx_idx_const = 0..accounts.len();
+            let title = name.to_string();
+            let acc_display = acc_display_gen(&ix.idl.accounts, true);
+            let args_display = args_display_gen(ix, true);
+            let trailing_idx_offset = accounts.len();
+            let known_checks: Vec<_> = ix
+                .idl
+                .accounts
+                .iter()
+                .flat_map(acc_item_flatten)
+                .filter_map(|acc| {
+                    let address = acc_address(acc, known_accounts, paths)?;
+                    let name = format_ident!("{}", acc.name.to_snake_case());
+                    Some(quote! {
+                        if keys.get(out.accounts.#name) != Some(&#address) {
+                            return Err(#anchor_interface::errors::TryAccountIndexesError
+                                ::KnownAccountMismatch(stringify!(#name), out.accounts.#name)
+                            );
+                        }
+                    })
+                })
+                .collect();
+            let keys_arg = if known_checks.is_empty() {
+                quote!(_keys)
+            } else {
+                quote!(keys)
+            };
+            let try_from_docs =
+                format!(" Decodes the instruction with the accounts named by `{accounts_name}`.");
+            let from_args: Vec<_> = ix.args.iter().map(|arg| &arg.ident).collect();
+            let from_pattern = if from_args.is_empty() {
                 quote!()
             } else {
-                quote!(mut)
+                quote!({ #(#from_args),* })
+            };
+            let arbitrary_impl = if fuzz {
+                let arbitrary_args = &ix.arbitrary_args;
+                let marker = if accounts.is_empty() {
+                    quote!(_marker: ::core::marker::PhantomData,)
+                } else {
+                    quote!()
+                };
+                let body = quote! {
+                    Self {
+                        program_id: #arbitrary_pubkey,
+                        accounts: #accounts_name {
+                            #(#accounts: #arbitrary_pubkey,)*
+                            #marker
+                        },
+                        trailing_accounts: (0..u.arbitrary_len::<[u8; 34]>()?)
+                            .map(|_| Ok(#account_meta {
+                                pubkey: #arbitrary_pubkey,
+                                is_signer: #arbitrary::Arbitrary::arbitrary(u)?,
+                                is_writable: #arbitrary::Arbitrary::arbitrary(u)?,
+                            }))
+                            .collect::<#arbitrary::Result<Vec<_>>>()?,
+                        #(#arbitrary_args,)*
+                    }
+                };
+                arbitrary_impl_gen(name, body, &arbitrary_cfg, paths)
+            } else {
+                quote!()
             };
-            let try_acc_idx_idx_const = 0..accounts.len();
-            let try_acc_idx_idx = 0..accounts.len();
             quote! {
+                #accounts_struct
+
+                #docs
                 #[derive(Debug)]
                 pub struct #name {
-                    pub program_id: ::solana_program::pubkey::Pubkey,
+                    pub program_id: #pubkey,
 
                     // Accounts
-                    #(pub #accounts_decl: ::solana_program::pubkey::Pubkey,)*
-                    pub trailing_accounts: Vec<::solana_program::instruction::AccountMeta>,
+                    pub accounts: #accounts_name<#pubkey>,
+                    pub trailing_accounts: Vec<#account_meta>,
 
                     // Params
                     #(#params_decl,)*
                 }
                 impl #name {
-                    pub fn into_instruction(self) -> ::solana_program::instruction::Instruction {
+                    pub fn into_instruction(self) -> #instruction {
                         let Self {
                             program_id,
-                            #(#accounts,)*
+                            accounts: #accounts_name {
+                                #(#accounts,)*
+                                ..
+                            },
                             trailing_accounts,
                             #(#params,)*
                         } = self;
@@ -323,7 +1229,7 @@ fn ix_builders_and_parsers_gen(master_enum_name: &Ident, ixs: &[Instruction<'_>]
                         }
                         .pack();
 
-                        ::solana_program::instruction::Instruction {
+                        #instruction {
                             program_id,
                             data,
                             accounts,
@@ -331,32 +1237,455 @@ fn ix_builders_and_parsers_gen(master_enum_name: &Ident, ixs: &[Instruction<'_>]
                     }
                 }
 
+                #display_cfg
+                impl ::core::fmt::Display for #name {
+                    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                        write!(f, #title)?;
+                        write!(f, "\n  program id: {}", self.program_id)?;
+                        #acc_display
+                        #args_display
+                        if !self.trailing_accounts.is_empty() {
+                            write!(f, "\n  trailing accounts:")?;
+                            for (idx, meta) in (#trailing_idx_offset..).zip(&self.trailing_accounts) {
+                                let markers = match (meta.is_signer, meta.is_writable) {
+                                    (true, true) => "[signer, writable]",
+                                    (true, false) => "[signer]",
+                                    (false, true) => "[writable]",
+                                    (false, false) => "[]",
+                                };
+                                write!(f, "\n    {}. {} {}", idx, markers, meta.pubkey)?;
+                            }
+                        }
+                        Ok(())
+                    }
+                }
+
+                #account_indexes_cfg
                 #[derive(Debug)]
                 pub struct #account_idxs_name {
-                    #(pub #accounts_decl: usize,)*
+                    pub accounts: #accounts_name<usize>,
                     pub trailing_accounts: Vec<usize>,
                 }
+                #account_indexes_cfg
                 impl #account_idxs_name {
                     #(
                         pub const #upper_accounts: usize = #try_acc_idx_idx_const;
                     )*
                 }
+                #account_indexes_cfg
+                impl #account_idxs_name {
+                    /// Same as `try_from(indexes)`, but also checks the accounts with
+                    /// a fixed address against the instruction account `keys`.
+                    pub fn try_from_keys(
+                        indexes: &[u8],
+                        #keys_arg: &[#pubkey],
+                    ) -> Result<Self, #anchor_interface::errors::TryAccountIndexesError> {
+                        let out = Self::try_from(indexes)?;
+                        #(#known_checks)*
+                        Ok(out)
+                    }
+                }
+                #account_indexes_cfg
                 impl<'a> TryFrom<&'a [u8]> for #account_idxs_name {
-                    type Error = ::anchor_interface::errors::TryAccountIndexesError;
+                    type Error = #anchor_interface::errors::TryAccountIndexesError;
                     fn try_from(indexes: &'a [u8]) -> Result<Self, Self::Error> {
-                        let #try_acc_idx_iter_mut iter = indexes.iter().map(|idx| (*idx) as usize);
+                        let (accounts, trailing_accounts) = #accounts_name::from_slice(indexes)
+                            .ok_or_else(|| #anchor_interface::errors::TryAccountIndexesError
+                                ::GetIndex(#accounts_name::<u8>::NAMES[indexes.len()], indexes.len())
+                            )?;
                         Ok(Self {
-                            #(
-                                #try_acc_idx: iter.next()
-                                    .ok_or(::anchor_interface::errors::TryAccountIndexesError
-                                        ::GetIndex(stringify!(#try_acc_idx), #try_acc_idx_idx)
-                                    )?,
-                            )*
-                            trailing_accounts: iter.collect(),
+                            accounts: accounts.map(|idx| *idx as usize),
+                            trailing_accounts: trailing_accounts.iter().map(|idx| *idx as usize).collect(),
                         })
                     }
                 }
+
+                #account_indexes_cfg
+                impl TryFrom<&#instruction> for #name {
+                    type Error = std::io::Error;
+
+                    #[doc = #try_from_docs]
+                    fn try_from(ix: &#instruction) -> Result<Self, Self::Error> {
+                        match #master_enum_name::unpack(&ix.data)? {
+                            #master_enum_name::#name #from_pattern => {
+                                let (accounts, trailing_accounts) =
+                                    #accounts_name::from_slice(&ix.accounts).ok_or_else(|| {
+                                        std::io::Error::new(
+                                            std::io::ErrorKind::InvalidInput,
+                                            format!(
+                                                "missing account `{}`",
+                                                #accounts_name::<#account_meta>::NAMES[ix.accounts.len()],
+                                            ),
+                                        )
+                                    })?;
+                                Ok(Self {
+                                    program_id: ix.program_id,
+                                    accounts: accounts.map(|meta| meta.pubkey),
+                                    trailing_accounts: trailing_accounts.to_vec(),
+                                    #(#from_args,)*
+                                })
+                            }
+                            #[allow(unreachable_patterns)]
+                            _ => Err(std::io::Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                concat!("not `", #title, "` instruction"),
+                            )),
+                        }
+                    }
+                }
+
+                #arbitrary_impl
             }
         })
         .collect()
 }
+
+fn ix_typed_builder_gen(
+    ix: &Instruction<'_>,
+    program_id: Option<&TokenStream>,
+    known_accounts: &BTreeMap<String, String>,
+    cfg: &TokenStream,
+    paths: &CratePaths,
+) -> TokenStream {
+    let pubkey = paths.pubkey();
+    let account_meta = paths.account_meta();
+    let anchor_interface = &paths.anchor_interface;
+    let name = &ix.ident;
+    let builder_name = format_ident!("{}Builder", name);
+    let accounts_name = format_ident!("{}Accounts", name);
+    let accounts: Vec<_> = ix.idl.accounts.iter().flat_map(acc_item_flatten).collect();
+    let account_names: Vec<_> = accounts
+        .iter()
+        .map(|acc| format_ident!("{}", acc.name.to_snake_case()))
+        .collect();
+    let account_docs: Vec<_> = accounts
+        .iter()
+        .map(|acc| acc_field_docs_gen(acc, known_accounts))
+        .collect();
+    let account_defaults =
+        accounts
+            .iter()
+            .map(|acc| match acc_address(acc, known_accounts, paths) {
+                Some(address) => quote!(Some(#address)),
+                None => quote!(None),
+            });
+    let marker = if accounts.is_empty() {
+        quote!(_marker: ::core::marker::PhantomData,)
+    } else {
+        quote!()
+    };
+    let program_id_default = match program_id {
+        Some(program_id) => quote!(Some(#program_id)),
+        None => quote!(None),
+    };
+    // NOTE: Anchor encodes the missing optional account as the program id
+    let optional_names: Vec<_> = accounts
+        .iter()
+        .filter(|acc| acc.optional)
+        .map(|acc| format_ident!("{}", acc.name.to_snake_case()))
+        .collect();
+    let accounts_mut = if optional_names.is_empty() {
+        quote!()
+    } else {
+        quote!(mut)
+    };
+    let arg_names: Vec<_> = ix.args.iter().map(|arg| &arg.ident).collect();
+    let arg_types: Vec<_> = ix.args.iter().map(|arg| &arg.ty).collect();
+    let arg_docs: Vec<_> = ix.args.iter().map(|arg| &arg.docs).collect();
+
+    quote! {
+        #cfg
+        impl #name {
+            pub fn builder() -> #builder_name {
+                #builder_name::default()
+            }
+        }
+
+        #cfg
+        #[derive(Debug, Clone)]
+        pub struct #builder_name {
+            program_id: Option<#pubkey>,
+            accounts: #accounts_name<Option<#pubkey>>,
+            trailing_accounts: Vec<#account_meta>,
+            #(
+                #arg_docs
+                #arg_names: Option<#arg_types>,
+            )*
+        }
+        #cfg
+        impl Default for #builder_name {
+            fn default() -> Self {
+                Self {
+                    program_id: #program_id_default,
+                    accounts: #accounts_name {
+                        #(#account_names: #account_defaults,)*
+                        #marker
+                    },
+                    trailing_accounts: Vec::new(),
+                    #(#arg_names: None,)*
+                }
+            }
+        }
+        #cfg
+        impl #builder_name {
+            pub fn program_id(mut self, program_id: #pubkey) -> Self {
+                self.program_id = Some(program_id);
+                self
+            }
+            #(
+                #account_docs
+                pub fn #account_names(mut self, #account_names: #pubkey) -> Self {
+                    self.accounts.#account_names = Some(#account_names);
+                    self
+                }
+            )*
+            pub fn trailing_accounts(
+                mut self,
+                trailing_accounts: Vec<#account_meta>,
+            ) -> Self {
+                self.trailing_accounts = trailing_accounts;
+                self
+            }
+            #(
+                #arg_docs
+                pub fn #arg_names(mut self, #arg_names: #arg_types) -> Self {
+                    self.#arg_names = Some(#arg_names);
+                    self
+                }
+            )*
+            /// Builds the instruction, the optional accounts not set are
+            /// the program id (Anchor's `None`).
+            pub fn build(self) -> Result<#name, #anchor_interface::errors::BuildInstructionError> {
+                let program_id = self.program_id
+                    .ok_or(#anchor_interface::errors::BuildInstructionError::MissingProgramId)?;
+                let #accounts_mut accounts = self.accounts;
+                #(
+                    accounts.#optional_names.get_or_insert(program_id);
+                )*
+                if let Some((name, _)) = accounts.iter().find(|(_, key)| key.is_none()) {
+                    return Err(#anchor_interface::errors::BuildInstructionError::MissingAccount(name));
+                }
+                Ok(#name {
+                    program_id,
+                    accounts: accounts.map(Option::unwrap_or_default),
+                    trailing_accounts: self.trailing_accounts,
+                    #(
+                        #arg_names: self.#arg_names
+                            .ok_or(#anchor_interface::errors::BuildInstructionError
+                                ::MissingArg(stringify!(#arg_names))
+                            )?,
+                    )*
+                })
+            }
+        }
+    }
+}
+
+/// Checks that the fields of the instructions have unique names: the args don't clash
+/// with the generated `program_id`, `accounts` and `trailing_accounts`.
+/// With the `builders`, the setters are checked too: the accounts and args
+/// don't clash with each other and with the generated `program_id`, `trailing_accounts`
+/// and `build`.
+pub(crate) fn check_instruction_names(
+    ixs: &[IdlInstruction],
+    builders: bool,
+) -> Result<(), GenerateError> {
+    let duplicate = |names: Vec<String>| {
+        let mut unique = BTreeSet::new();
+        names.into_iter().find(|name| !unique.insert(name.clone()))
+    };
+    for ix in ixs {
+        let args: Vec<_> = ix.args.iter().map(|arg| arg.name.to_snake_case()).collect();
+        let fields = ["program_id", "accounts", "trailing_accounts"]
+            .into_iter()
+            .map(String::from)
+            .chain(args.iter().cloned());
+        if let Some(name) = duplicate(fields.collect()) {
+            return Err(GenerateError::InstructionFieldClash {
+                instruction: ix.name.clone(),
+                name,
+            });
+        }
+        if !builders {
+            continue;
+        }
+        let accounts = ix.accounts.iter().flat_map(acc_item_flatten);
+        let setters = ["program_id", "trailing_accounts", "build"]
+            .into_iter()
+            .map(String::from)
+            .chain(accounts.map(|acc| acc.name.to_snake_case()))
+            .chain(args);
+        if let Some(name) = duplicate(setters.collect()) {
+            return Err(GenerateError::BuilderNameClash {
+                instruction: ix.name.clone(),
+                name,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::{generator, legacy_idl, ADDRESS};
+    use crate::{GenerateError, Generator, GeneratorOptions};
+
+    /// IDL with the `update` instruction of the `authority` and optional `oracle`
+    /// accounts and the `args` (`name: type`).
+    fn idl(args: &[(&str, &str)]) -> String {
+        let args: Vec<_> = args
+            .iter()
+            .map(|(name, ty)| format!(r#"{{ "name": "{name}", "type": "{ty}" }}"#))
+            .collect();
+        let instructions = format!(
+            r#"{{
+                "name": "update",
+                "discriminator": [1, 1, 1, 1, 1, 1, 1, 1],
+                "accounts": [
+                    {{ "name": "authority", "signer": true }},
+                    {{ "name": "oracle", "optional": true }}
+                ],
+                "args": [{}]
+            }}"#,
+            args.join(",")
+        );
+        crate::test_util::idl(&instructions, "", "")
+    }
+
+    fn gen_instructions(opts: GeneratorOptions, idl: &str) -> String {
+        generator(opts, idl).gen_instructions().to_string()
+    }
+
+    #[test]
+    fn display_arg_named_like_formatter() {
+        let ixs = gen_instructions(Default::default(), &idl(&[("f", "u64")]));
+        assert!(ixs.contains("Self :: Update { f : arg_f }"));
+        assert!(ixs.contains("arg_f) ?"));
+    }
+
+    #[test]
+    fn builder_optional_account_defaults_to_program_id() {
+        let opts = GeneratorOptions::builder().builders(true).build();
+        let ixs = gen_instructions(opts, &idl(&[("amount", "u64")]));
+        assert!(ixs.contains("accounts . oracle . get_or_insert (program_id) ;"));
+        assert!(!ixs.contains("accounts . authority . get_or_insert"));
+    }
+
+    #[test]
+    fn builder_name_clash() {
+        let opts = || GeneratorOptions::builder().builders(true).build();
+        for name in [
+            "authority",
+            "program_id",
+            "accounts",
+            "trailing_accounts",
+            "build",
+        ] {
+            let err = Generator::with_idl_str(opts(), &idl(&[(name, "u64")]))
+                .err()
+                .unwrap()
+                .to_string();
+            assert!(err.contains(&format!("`{name}` twice")), "{err}");
+        }
+        assert!(Generator::with_idl_str(opts(), &idl(&[("amount", "u64")])).is_ok());
+        // no builders to clash
+        assert!(Generator::with_idl_str(Default::default(), &idl(&[("build", "u64")])).is_ok());
+    }
+
+    #[test]
+    fn instruction_field_clash() {
+        for name in ["program_id", "accounts", "trailing_accounts"] {
+            let err = Generator::with_idl_str(Default::default(), &idl(&[(name, "u64")]))
+                .err()
+                .unwrap();
+            assert!(
+                matches!(&err, GenerateError::InstructionFieldClash { name: clash, .. } if clash == name),
+                "{err}"
+            );
+        }
+    }
+
+    /// Check of the known account address in `try_from_keys`.
+    fn known_check(name: &str) -> String {
+        format!("KnownAccountMismatch (stringify ! ({name})")
+    }
+
+    #[test]
+    fn known_accounts_of_current_spec() {
+        // `token_program` of ratex has no IDL address, it's either Token or Token-2022
+        const RATEX_IDL: &str = include_str!("../../examples/ratex-interface/ratex.json");
+        let opts = GeneratorOptions::builder().builders(true).build();
+        let ixs = gen_instructions(opts, RATEX_IDL);
+        assert!(!ixs.contains(&known_check("token_program")));
+        assert!(ixs.contains(&known_check("system_program")));
+
+        let opts = GeneratorOptions::builder()
+            .builders(true)
+            .known_accounts(&[(
+                "token_program",
+                "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb",
+            )])
+            .build();
+        let ixs = gen_instructions(opts, RATEX_IDL);
+        assert!(ixs.contains(&known_check("token_program")));
+    }
+
+    #[test]
+    fn known_accounts_of_legacy_idl() {
+        let idl = legacy_idl(
+            r#"{
+                "name": "transfer",
+                "accounts": [{ "name": "tokenProgram", "isMut": false, "isSigner": false }],
+                "args": []
+            }"#,
+            "",
+        );
+        let ixs = gen_instructions(Default::default(), &idl);
+        assert!(ixs.contains(&known_check("token_program")));
+
+        let opts = GeneratorOptions::builder()
+            .known_accounts(&[("token_program", "")])
+            .build();
+        let ixs = gen_instructions(opts, &idl);
+        assert!(!ixs.contains(&known_check("token_program")));
+    }
+
+    #[test]
+    fn known_address_docs() {
+        let ixs = gen_instructions(
+            GeneratorOptions::builder()
+                .known_accounts(&[("authority", ADDRESS)])
+                .build(),
+            &idl(&[]),
+        );
+        assert!(ixs.contains("Known address: `11111111111111111111111111111111` (default"));
+        assert!(!ixs.contains("- Address:"));
+    }
+
+    #[test]
+    fn decode_checks_account_indexes() {
+        let tests = generator(Default::default(), &idl(&[]))
+            .gen_instruction_tests()
+            .to_string();
+        assert!(tests.contains(
+            "assert_eq ! (decoded . accounts . authority , keys [UpdateAccountIndexes :: AUTHORITY]) ;"
+        ));
+        assert!(tests.contains(
+            "assert_eq ! (decoded . accounts . oracle , keys [UpdateAccountIndexes :: ORACLE]) ;"
+        ));
+    }
+
+    #[test]
+    fn introspection_is_opt_in() {
+        // legacy IDL without the address
+        let idl = legacy_idl(r#"{ "name": "update", "accounts": [], "args": [] }"#, "");
+        let ixs = gen_instructions(Default::default(), &idl);
+        assert!(!ixs.contains("SysvarInstruction"));
+        assert!(!ixs.contains("compile_error"));
+
+        let opts = GeneratorOptions::builder().introspection(true).build();
+        let ixs = gen_instructions(opts, &idl);
+        assert!(ixs.contains("pub struct SysvarInstruction"));
+        assert!(ixs.contains("compile_error"));
+    }
+}
@@ -0,0 +1,204 @@
+use anchor_lang_idl::types::{IdlArrayLen, IdlDefinedFields, IdlType, IdlTypeDef, IdlTypeDefTy};
+
+/// Alignment of 128 and 256 bit integers on-chain (`bpf`/`sbf` targets).
+pub const ON_CHAIN_INT128_ALIGN: usize = 8;
+
+/// Borsh and zero-copy layouts of the IDL types.
+pub struct TypeLayouts<'a> {
+    types: &'a [IdlTypeDef],
+    /// Alignment of 128 and 256 bit integers, differs between the targets.
+    int128_align: usize,
+}
+
+impl<'a> TypeLayouts<'a> {
+    pub fn new(types: &'a [IdlTypeDef], int128_align: usize) -> Self {
+        Self {
+            types,
+            int128_align,
+        }
+    }
+
+    /// Layouts on the current host, e.g. 16 bytes aligned `u128` on `x86_64`.
+    pub fn host(types: &'a [IdlTypeDef]) -> Self {
+        Self::new(types, std::mem::align_of::<u128>())
+    }
+
+    fn typedef(&self, name: &str) -> Result<&'a IdlTypeDef, String> {
+        self.types
+            .iter()
+            .find(|def| def.name == name)
+            .ok_or_else(|| format!("unknown type `{name}`"))
+    }
+
+    /// Size and alignment of the zero-copy type, `packed` tells the packed structs.
+    pub fn pod(
+        &self,
+        ty: &IdlType,
+        packed: &dyn Fn(&str) -> bool,
+    ) -> Result<(usize, usize), String> {
+        Ok(match ty {
+            IdlType::Bool | IdlType::U8 | IdlType::I8 => (1, 1),
+            IdlType::U16 | IdlType::I16 => (2, 2),
+            IdlType::U32 | IdlType::I32 | IdlType::F32 => (4, 4),
+            IdlType::U64 | IdlType::I64 | IdlType::F64 => (8, 8),
+            IdlType::U128 | IdlType::I128 => (16, self.int128_align),
+            IdlType::U256 | IdlType::I256 => (32, self.int128_align),
+            IdlType::Pubkey => (32, 1),
+            IdlType::Array(inner, len) => {
+                let (size, align) = self.pod(inner, packed)?;
+                (size * array_len(len)?, align)
+            }
+            IdlType::Defined { name, .. } => match &self.typedef(name)?.ty {
+                IdlTypeDefTy::Struct { fields } => {
+                    let packed_struct = packed(name);
+                    let (mut size, mut align) = (0usize, 1);
+                    for ty in defined_fields_types(fields) {
+                        let (field_size, field_align) = self.pod(ty, packed)?;
+                        let field_align = if packed_struct { 1 } else { field_align };
+                        size = size.next_multiple_of(field_align) + field_size;
+                        align = align.max(field_align);
+                    }
+                    (size.next_multiple_of(align), align)
+                }
+                IdlTypeDefTy::Enum { variants }
+                    if variants.iter().all(|var| var.fields.is_none()) =>
+                {
+                    (1, 1)
+                }
+                IdlTypeDefTy::Enum { .. } => {
+                    return Err(format!("enum `{name}` with fields isn't zero-copy"))
+                }
+                IdlTypeDefTy::Type { alias } => self.pod(alias, packed)?,
+            },
+            ty => return Err(format!("`{ty:?}` isn't zero-copy")),
+        })
+    }
+
+    /// Max borsh size of the type with empty vectors and strings,
+    /// i.e. the size of the zeroed data it's decoded from.
+    pub fn borsh_fixed_size(&self, ty: &IdlType) -> Result<usize, String> {
+        Ok(match ty {
+            IdlType::Bool | IdlType::U8 | IdlType::I8 => 1,
+            IdlType::U16 | IdlType::I16 => 2,
+            IdlType::U32 | IdlType::I32 | IdlType::F32 => 4,
+            IdlType::U64 | IdlType::I64 | IdlType::F64 => 8,
+            IdlType::U128 | IdlType::I128 => 16,
+            IdlType::U256 | IdlType::I256 | IdlType::Pubkey => 32,
+            // length prefix
+            IdlType::Bytes | IdlType::String | IdlType::Vec(_) => 4,
+            IdlType::Option(inner) => 1 + self.borsh_fixed_size(inner)?,
+            IdlType::Array(inner, len) => self.borsh_fixed_size(inner)? * array_len(len)?,
+            IdlType::Defined { name, .. } => match &self.typedef(name)?.ty {
+                IdlTypeDefTy::Struct { fields } => self.borsh_fields_size(fields)?,
+                IdlTypeDefTy::Enum { variants } => {
+                    let mut size = 0;
+                    for var in variants {
+                        size = size.max(self.borsh_fields_size(&var.fields)?);
+                    }
+                    1 + size
+                }
+                IdlTypeDefTy::Type { alias } => self.borsh_fixed_size(alias)?,
+            },
+            ty => return Err(format!("`{ty:?}` not supported")),
+        })
+    }
+
+    fn borsh_fields_size(&self, fields: &Option<IdlDefinedFields>) -> Result<usize, String> {
+        defined_fields_types(fields)
+            .into_iter()
+            .map(|ty| self.borsh_fixed_size(ty))
+            .sum()
+    }
+
+    /// Decodes the borsh value of the type at `offset`, advancing it.
+    pub fn borsh_decode(
+        &self,
+        ty: &IdlType,
+        data: &[u8],
+        offset: &mut usize,
+    ) -> Result<(), String> {
+        match ty {
+            IdlType::Bool => match take(data, offset, 1)?[0] {
+                0 | 1 => {}
+                value => return Err(format!("invalid bool {value} at {}", *offset - 1)),
+            },
+            IdlType::Bytes => {
+                let len = take_len(data, offset)?;
+                take(data, offset, len)?;
+            }
+            IdlType::String => {
+                let len = take_len(data, offset)?;
+                let start = *offset;
+                std::str::from_utf8(take(data, offset, len)?)
+                    .map_err(|err| format!("invalid string at {start}: {err}"))?;
+            }
+            IdlType::Option(inner) => match take(data, offset, 1)?[0] {
+                0 => {}
+                1 => self.borsh_decode(inner, data, offset)?,
+                tag => return Err(format!("invalid option tag {tag} at {}", *offset - 1)),
+            },
+            IdlType::Vec(inner) => {
+                for _ in 0..take_len(data, offset)? {
+                    self.borsh_decode(inner, data, offset)?;
+                }
+            }
+            IdlType::Array(inner, len) => {
+                for _ in 0..array_len(len)? {
+                    self.borsh_decode(inner, data, offset)?;
+                }
+            }
+            IdlType::Defined { name, .. } => match &self.typedef(name)?.ty {
+                IdlTypeDefTy::Struct { fields } => {
+                    for ty in defined_fields_types(fields) {
+                        self.borsh_decode(ty, data, offset)?;
+                    }
+                }
+                IdlTypeDefTy::Enum { variants } => {
+                    let tag = take(data, offset, 1)?[0];
+                    let variant = variants.get(tag as usize).ok_or_else(|| {
+                        format!("invalid `{name}` variant {tag} at {}", *offset - 1)
+                    })?;
+                    for ty in defined_fields_types(&variant.fields) {
+                        self.borsh_decode(ty, data, offset)?;
+                    }
+                }
+                IdlTypeDefTy::Type { alias } => self.borsh_decode(alias, data, offset)?,
+            },
+            // the rest are fixed-size primitives
+            ty => {
+                let size = self.borsh_fixed_size(ty)?;
+                take(data, offset, size)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn defined_fields_types(fields: &Option<IdlDefinedFields>) -> Vec<&IdlType> {
+    match fields {
+        Some(IdlDefinedFields::Named(fields)) => fields.iter().map(|field| &field.ty).collect(),
+        Some(IdlDefinedFields::Tuple(types)) => types.iter().collect(),
+        None => vec![],
+    }
+}
+
+fn array_len(len: &IdlArrayLen) -> Result<usize, String> {
+    match len {
+        IdlArrayLen::Value(len) => Ok(*len),
+        IdlArrayLen::Generic(len) => Err(format!("generic array length `{len}`")),
+    }
+}
+
+fn take<'d>(data: &'d [u8], offset: &mut usize, len: usize) -> Result<&'d [u8], String> {
+    let bytes = data
+        .get(*offset..)
+        .and_then(|rest| rest.get(..len))
+        .ok_or_else(|| format!("unexpected end of data reading {len} bytes at {offset}"))?;
+    *offset += len;
+    Ok(bytes)
+}
+
+fn take_len(data: &[u8], offset: &mut usize) -> Result<usize, String> {
+    let len = take(data, offset, 4)?;
+    Ok(u32::from_le_bytes(len.try_into().unwrap()) as usize)
+}
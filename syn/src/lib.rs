@@ -4,9 +4,10 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use anchor_lang_idl::types::{Idl, IdlRepr};
-use common::item_gen;
+use anchor_lang_idl::types::{Idl, IdlDefinedFields, IdlRepr, IdlTypeDefTy};
+use common::{cfg_feature_gen, item_gen, pubkey_array_gen, pubkey_bytes, pubkey_gen};
 use darling::{util::PathList, FromMeta};
+use heck::ToSnakeCase;
 use proc_macro2::{Ident, TokenStream};
 use quote::{format_ident, quote};
 use syn::Meta;
@@ -14,26 +15,46 @@ use typed_builder::TypedBuilder;
 
 use rust_format::{Formatter, PrettyPlease};
 
+pub use build::{build, GenerateError};
+
+pub mod build;
 pub mod common;
+pub mod filter;
+pub mod idl;
+pub mod layout;
 pub mod macros;
 
 pub mod accounts;
 pub mod errors;
 pub mod exports;
+pub mod fuzz;
 pub mod instructions;
+pub mod processor;
+pub mod tests;
 pub mod typedefs;
+pub mod validate;
+
+#[cfg(test)]
+mod test_util;
 
 #[derive(Default, FromMeta, TypedBuilder)]
 pub struct GeneratorOptions {
     /// Module name.
+    #[builder(default, setter(strip_option, into))]
     pub out_mod: Option<String>,
     /// Path to the out module directory.
+    #[builder(default, setter(strip_option, into))]
     pub out_dir: Option<String>,
 
     /// Path to the IDL.
-    #[builder(setter(into))]
+    #[builder(default, setter(into))]
     pub idl: String,
 
+    /// Program address, required by the legacy IDLs without one
+    /// (overrides the IDL address).
+    #[builder(default, setter(strip_option, into))]
+    pub program_id: Option<String>,
+
     /// List of structs with implemented `borsh` always.
     #[builder(default, setter(transform = |list: &[&str]| {
         Some(parse_path_list(list))
@@ -52,11 +73,176 @@ pub struct GeneratorOptions {
     }))]
     pub packed: Option<PathList>,
 
+    /// Generate only the listed instructions.
+    #[builder(default, setter(transform = |list: &[&str]| {
+        Some(parse_path_list(list))
+    }))]
+    pub only_instructions: Option<PathList>,
+
+    /// Skip the listed instructions.
+    #[builder(default, setter(transform = |list: &[&str]| {
+        Some(parse_path_list(list))
+    }))]
+    pub skip_instructions: Option<PathList>,
+
+    /// Generate only the listed accounts.
+    #[builder(default, setter(transform = |list: &[&str]| {
+        Some(parse_path_list(list))
+    }))]
+    pub only_accounts: Option<PathList>,
+
+    /// Skip the listed accounts.
+    #[builder(default, setter(transform = |list: &[&str]| {
+        Some(parse_path_list(list))
+    }))]
+    pub skip_accounts: Option<PathList>,
+
     /// List of additional attributes.
     #[darling(multiple)]
+    #[builder(default)]
     pub attr: Vec<AttrOptions>,
+
+    /// Generate `<Instruction>::builder()` for every instruction.
+    #[darling(default)]
+    #[builder(default)]
+    pub builders: bool,
+
+    /// Generate a `#[cfg(test)]` module with round-trip tests of the interface.
+    #[darling(default)]
+    #[builder(default)]
+    pub gen_tests: bool,
+
+    /// Generate `arbitrary::Arbitrary` implementations of the types, accounts and
+    /// instructions, and the `fuzz` module with the parsers entry point.
+    #[darling(default)]
+    #[builder(default)]
+    pub fuzz: bool,
+
+    /// Generate the `processor` module with the `<Program>Processor` trait
+    /// and the `process_instruction` entrypoint dispatching to it.
+    #[darling(default)]
+    #[builder(default)]
+    pub processor: bool,
+
+    /// Generate `SysvarInstruction` loading the program instructions of the current
+    /// transaction from the instructions sysvar (needs the program id).
+    #[darling(default)]
+    #[builder(default)]
+    pub introspection: bool,
+
+    /// Cargo features gating the generated modules and helpers.
+    #[darling(default)]
+    #[builder(default)]
+    pub features: FeatureOptions,
+
+    /// Fixed addresses of instruction accounts by name, applied to the accounts
+    /// without an IDL address (overrides [`DEFAULT_KNOWN_ACCOUNTS`] of the legacy IDLs,
+    /// empty address disables the default).
+    #[builder(default, setter(transform = |list: &[(&str, &str)]| {
+        Some(list.iter().map(|(name, address)| (name.to_string(), address.to_string())).collect())
+    }))]
+    pub known_accounts: Option<HashMap<String, String>>,
+
+    /// IDL types replaced with the existing Rust types, not generated.
+    #[builder(default, setter(transform = |list: &[(&str, ExternType)]| {
+        Some(list.iter().map(|(name, ty)| (name.to_string(), ty.clone())).collect())
+    }))]
+    pub extern_types: Option<HashMap<String, ExternType>>,
+
+    /// Field types replaced with the wrappers of the same layout,
+    /// `Type::field` for types and accounts, `instruction::arg` for instruction args.
+    #[darling(rename = "field_type")]
+    #[builder(default, setter(transform = |list: &[(&str, &str)]| {
+        Some(list.iter().map(|(field, ty)| (field.to_string(), parse_field_type(ty))).collect())
+    }))]
+    pub field_types: Option<HashMap<String, syn::Type>>,
+
+    /// Path to the `solana_program` crate (default `<anchor_interface>::__private::solana_program`).
+    #[builder(default, setter(transform = |path: &str| Some(parse_crate_path(path))))]
+    pub solana_program: Option<syn::Path>,
+
+    /// Path to the module with `Pubkey` (default `<solana_program>::pubkey`).
+    #[builder(default, setter(transform = |path: &str| Some(parse_crate_path(path))))]
+    pub solana_pubkey: Option<syn::Path>,
+
+    /// Path to the module with `Instruction` and `AccountMeta` (default `<solana_program>::instruction`).
+    #[builder(default, setter(transform = |path: &str| Some(parse_crate_path(path))))]
+    pub solana_instruction: Option<syn::Path>,
+
+    /// Path to the module with `ProgramError` (default `<solana_program>::program_error`).
+    #[builder(default, setter(transform = |path: &str| Some(parse_crate_path(path))))]
+    pub solana_program_error: Option<syn::Path>,
+
+    /// Path to the module with `DecodeError` (default `<solana_program>::decode_error`).
+    #[builder(default, setter(transform = |path: &str| Some(parse_crate_path(path))))]
+    pub solana_decode_error: Option<syn::Path>,
+
+    /// Path to the module with `AccountInfo` (default `<solana_program>::account_info`).
+    #[builder(default, setter(transform = |path: &str| Some(parse_crate_path(path))))]
+    pub solana_account_info: Option<syn::Path>,
+
+    /// Path to the instructions sysvar module with `load_instruction_at_checked`
+    /// (default `<solana_program>::sysvar::instructions`).
+    #[builder(default, setter(transform = |path: &str| Some(parse_crate_path(path))))]
+    pub solana_instructions_sysvar: Option<syn::Path>,
+
+    /// Path to the `borsh` crate (default `<anchor_interface>::__private::borsh`).
+    #[builder(default, setter(transform = |path: &str| Some(parse_crate_path(path))))]
+    pub borsh: Option<syn::Path>,
+
+    /// Path to the `bytemuck` crate (default `<anchor_interface>::__private::bytemuck`).
+    #[builder(default, setter(transform = |path: &str| Some(parse_crate_path(path))))]
+    pub bytemuck: Option<syn::Path>,
+
+    /// Path to the `num_derive` crate (default `<anchor_interface>::__private::num_derive`).
+    #[builder(default, setter(transform = |path: &str| Some(parse_crate_path(path))))]
+    pub num_derive: Option<syn::Path>,
+
+    /// Path to the `num_traits` crate (default `<anchor_interface>::__private::num_traits`).
+    #[builder(default, setter(transform = |path: &str| Some(parse_crate_path(path))))]
+    pub num_traits: Option<syn::Path>,
+
+    /// Path to the `thiserror` crate (default `<anchor_interface>::__private::thiserror`).
+    #[builder(default, setter(transform = |path: &str| Some(parse_crate_path(path))))]
+    pub thiserror: Option<syn::Path>,
+
+    /// Path to the `arbitrary` crate (default `<anchor_interface>::__private::arbitrary`).
+    #[builder(default, setter(transform = |path: &str| Some(parse_crate_path(path))))]
+    pub arbitrary: Option<syn::Path>,
+
+    /// Path to the `anchor_interface` crate (default `::anchor_interface`).
+    #[builder(default, setter(transform = |path: &str| Some(parse_crate_path(path))))]
+    pub anchor_interface: Option<syn::Path>,
 }
 
+/// Well-known program and sysvar accounts by (snake case) instruction account name,
+/// the defaults of the legacy IDLs, which don't have the account addresses.
+pub const DEFAULT_KNOWN_ACCOUNTS: &[(&str, &str)] = &[
+    ("system_program", "11111111111111111111111111111111"),
+    (
+        "token_program",
+        "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+    ),
+    (
+        "token_2022_program",
+        "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb",
+    ),
+    (
+        "associated_token_program",
+        "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL",
+    ),
+    (
+        "metadata_program",
+        "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s",
+    ),
+    (
+        "memo_program",
+        "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr",
+    ),
+    ("rent", "SysvarRent111111111111111111111111111111111"),
+    ("clock", "SysvarC1ock11111111111111111111111111111111"),
+];
+
 #[derive(FromMeta, TypedBuilder)]
 pub struct AttrOptions {
     #[darling(multiple, with = parse_submeta)]
@@ -67,6 +253,111 @@ pub struct AttrOptions {
     pub names: PathList,
 }
 
+/// Cargo feature names gating the generated items, not gated if not set.
+#[derive(Clone, Debug, Default, FromMeta)]
+pub struct FeatureOptions {
+    /// `instruction` module.
+    pub instruction: Option<String>,
+    /// `state` module.
+    pub state: Option<String>,
+    /// `types` module.
+    pub types: Option<String>,
+    /// `error` module.
+    pub error: Option<String>,
+    /// Instruction builders (`<Instruction>::builder()`).
+    pub builders: Option<String>,
+    /// `<Instruction>AccountIndexes` parsers.
+    pub account_indexes: Option<String>,
+    /// `Display` implementations of the instructions.
+    pub display: Option<String>,
+    /// `Arbitrary` implementations and the `fuzz` module.
+    pub fuzz: Option<String>,
+    /// Instructions sysvar introspection (`SysvarInstruction`).
+    pub introspection: Option<String>,
+    /// `processor` module.
+    pub processor: Option<String>,
+}
+
+/// Existing Rust type used instead of the IDL type,
+/// `Name = "path::to::Type"` or `Name(path = "path::to::Type", copy, default, pod)`.
+#[derive(Clone, Debug)]
+pub struct ExternType {
+    pub path: syn::Path,
+    /// Implements `Copy`.
+    pub copy: bool,
+    /// Implements `Default`.
+    pub default: bool,
+    /// Implements `bytemuck::Pod` (implies `copy`).
+    pub pod: bool,
+}
+
+impl ExternType {
+    /// Panics on the invalid path, see [`ExternType::try_new`].
+    pub fn new(path: &str) -> Self {
+        Self::try_new(path).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_new(path: &str) -> syn::Result<Self> {
+        Ok(Self {
+            path: try_parse_crate_path(path)?,
+            copy: false,
+            default: false,
+            pod: false,
+        })
+    }
+
+    pub fn copy(self) -> Self {
+        Self { copy: true, ..self }
+    }
+
+    pub fn default(self) -> Self {
+        Self {
+            default: true,
+            ..self
+        }
+    }
+
+    pub fn pod(self) -> Self {
+        Self { pod: true, ..self }
+    }
+}
+
+impl FromMeta for ExternType {
+    fn from_string(value: &str) -> darling::Result<Self> {
+        Ok(Self {
+            path: syn::parse_str(value)?,
+            copy: false,
+            default: false,
+            pod: false,
+        })
+    }
+
+    fn from_list(items: &[darling::ast::NestedMeta]) -> darling::Result<Self> {
+        #[derive(FromMeta)]
+        struct ExternTypeList {
+            path: syn::Path,
+            #[darling(default)]
+            copy: bool,
+            #[darling(default)]
+            default: bool,
+            #[darling(default)]
+            pod: bool,
+        }
+        let ExternTypeList {
+            path,
+            copy,
+            default,
+            pod,
+        } = ExternTypeList::from_list(items)?;
+        Ok(Self {
+            path,
+            copy,
+            default,
+            pod,
+        })
+    }
+}
+
 pub fn parse_submeta(meta: &Meta) -> darling::Result<Meta> {
     match meta {
         Meta::Path(_) => Err(darling::Error::unsupported_format("path").with_span(meta)),
@@ -75,24 +366,196 @@ pub fn parse_submeta(meta: &Meta) -> darling::Result<Meta> {
     }
 }
 
+/// Panics on the invalid names, see [`try_parse_path_list`].
 pub fn parse_path_list(list: &[&str]) -> PathList {
-    let list: Vec<syn::Path> = list
+    try_parse_path_list(list).unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Parses the list of type, account or instruction names (single identifiers).
+pub fn try_parse_path_list(list: &[&str]) -> syn::Result<PathList> {
+    let list = list
         .iter()
-        .map(|&path| {
-            let leading_colon = path
-                .starts_with("..")
-                .then_some(<syn::Token![::]>::default());
-            let idents = path
-                .split("::")
-                .map(|ident| syn::PathSegment::from(format_ident!("{}", ident)));
-
-            syn::Path {
-                leading_colon,
-                segments: syn::punctuated::Punctuated::from_iter(idents),
-            }
+        .map(|&name| {
+            // NOTE: keywords are accepted as the identifiers
+            syn::parse::Parser::parse_str(<Ident as syn::ext::IdentExt>::parse_any, name)
+                .map(syn::Path::from)
+                .map_err(|err| {
+                    syn::Error::new(
+                        err.span(),
+                        format!("parse name `{name}`, expected an identifier: {err}"),
+                    )
+                })
         })
-        .collect();
-    PathList::new(list)
+        .collect::<syn::Result<Vec<_>>>()?;
+    Ok(PathList::new(list))
+}
+
+/// Panics on the invalid path, see [`try_parse_crate_path`].
+pub fn parse_crate_path(path: &str) -> syn::Path {
+    try_parse_crate_path(path).unwrap_or_else(|err| panic!("{err}"))
+}
+
+pub fn try_parse_crate_path(path: &str) -> syn::Result<syn::Path> {
+    syn::parse_str(path)
+        .map_err(|err| syn::Error::new(err.span(), format!("parse crate path `{path}`: {err}")))
+}
+
+/// Panics on the invalid type, see [`try_parse_field_type`].
+pub fn parse_field_type(ty: &str) -> syn::Type {
+    try_parse_field_type(ty).unwrap_or_else(|err| panic!("{err}"))
+}
+
+pub fn try_parse_field_type(ty: &str) -> syn::Result<syn::Type> {
+    syn::parse_str(ty)
+        .map_err(|err| syn::Error::new(err.span(), format!("parse field type `{ty}`: {err}")))
+}
+
+/// Paths to the crates and external types used by generated code.
+#[derive(Clone)]
+pub struct CratePaths {
+    pub solana_pubkey: syn::Path,
+    pub solana_instruction: syn::Path,
+    pub solana_program_error: syn::Path,
+    pub solana_decode_error: syn::Path,
+    pub solana_account_info: syn::Path,
+    pub solana_instructions_sysvar: syn::Path,
+    pub borsh: syn::Path,
+    pub bytemuck: syn::Path,
+    pub num_derive: syn::Path,
+    pub num_traits: syn::Path,
+    pub thiserror: syn::Path,
+    pub arbitrary: syn::Path,
+    pub anchor_interface: syn::Path,
+    /// Existing types by IDL type name (see [`GeneratorOptions::extern_types`]).
+    pub extern_types: BTreeMap<Ident, ExternType>,
+}
+
+impl Default for CratePaths {
+    fn default() -> Self {
+        Self::new(&GeneratorOptions::default())
+    }
+}
+
+impl CratePaths {
+    pub fn new(opt: &GeneratorOptions) -> Self {
+        let anchor_interface = opt
+            .anchor_interface
+            .clone()
+            .unwrap_or_else(|| parse_crate_path("::anchor_interface"));
+        let path = |path: &Option<syn::Path>, name: &str| {
+            let name = format_ident!("{name}");
+            path.clone()
+                .unwrap_or_else(|| syn::parse_quote!(#anchor_interface::__private::#name))
+        };
+        let solana_program = path(&opt.solana_program, "solana_program");
+        let solana_module = |path: &Option<syn::Path>, module: &str| {
+            let module = format_ident!("{module}");
+            path.clone()
+                .unwrap_or_else(|| syn::parse_quote!(#solana_program::#module))
+        };
+        Self {
+            solana_pubkey: solana_module(&opt.solana_pubkey, "pubkey"),
+            solana_instruction: solana_module(&opt.solana_instruction, "instruction"),
+            solana_program_error: solana_module(&opt.solana_program_error, "program_error"),
+            solana_decode_error: solana_module(&opt.solana_decode_error, "decode_error"),
+            solana_account_info: solana_module(&opt.solana_account_info, "account_info"),
+            solana_instructions_sysvar: opt
+                .solana_instructions_sysvar
+                .clone()
+                .unwrap_or_else(|| syn::parse_quote!(#solana_program::sysvar::instructions)),
+            borsh: path(&opt.borsh, "borsh"),
+            bytemuck: path(&opt.bytemuck, "bytemuck"),
+            num_derive: path(&opt.num_derive, "num_derive"),
+            num_traits: path(&opt.num_traits, "num_traits"),
+            thiserror: path(&opt.thiserror, "thiserror"),
+            arbitrary: path(&opt.arbitrary, "arbitrary"),
+            anchor_interface,
+            extern_types: opt
+                .extern_types
+                .iter()
+                .flatten()
+                .map(|(name, ty)| (item_gen(name), ty.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn pubkey(&self) -> TokenStream {
+        let solana_pubkey = &self.solana_pubkey;
+        quote!(#solana_pubkey::Pubkey)
+    }
+
+    pub fn account_meta(&self) -> TokenStream {
+        let solana_instruction = &self.solana_instruction;
+        quote!(#solana_instruction::AccountMeta)
+    }
+
+    pub fn instruction(&self) -> TokenStream {
+        let solana_instruction = &self.solana_instruction;
+        quote!(#solana_instruction::Instruction)
+    }
+
+    pub fn account_info(&self) -> TokenStream {
+        let solana_account_info = &self.solana_account_info;
+        quote!(#solana_account_info::AccountInfo)
+    }
+
+    pub fn program_error(&self) -> TokenStream {
+        let solana_program_error = &self.solana_program_error;
+        quote!(#solana_program_error::ProgramError)
+    }
+
+    /// Wraps the `items` in `__cfg_solana_program!` if any of the `paths` is
+    /// a re-export of `anchor-interface`, so the items using them are expanded
+    /// with its `solana-program` feature only.
+    pub fn cfg_solana_program_gen(&self, paths: &[&syn::Path], items: TokenStream) -> TokenStream {
+        let anchor_interface = &self.anchor_interface;
+        let private = format!("{}::__private::", path_to_string(anchor_interface));
+        if paths
+            .iter()
+            .any(|path| path_to_string(path).starts_with(&private))
+        {
+            quote!(#anchor_interface::__cfg_solana_program! { #items })
+        } else {
+            items
+        }
+    }
+
+    /// `#[borsh(crate = "...")]` for derives, if `borsh` isn't used directly.
+    pub fn borsh_crate_attr(&self) -> TokenStream {
+        crate_attr(quote!(borsh), &self.borsh, "::borsh")
+    }
+
+    /// `#[bytemuck(crate = "...")]` for derives, if `bytemuck` isn't used directly.
+    pub fn bytemuck_crate_attr(&self) -> TokenStream {
+        crate_attr(quote!(bytemuck), &self.bytemuck, "::bytemuck")
+    }
+
+    /// Import and `#[num_traits = "..."]` for `FromPrimitive` derive,
+    /// if `num_traits` isn't used directly (the derive accepts identifier only).
+    pub fn num_traits_crate_attr(&self) -> (TokenStream, TokenStream) {
+        let num_traits = &self.num_traits;
+        if path_to_string(num_traits) == "::num_traits" {
+            (quote!(), quote!())
+        } else {
+            (
+                quote!(use #num_traits as num_traits_crate;),
+                quote!(#[num_traits = "num_traits_crate"]),
+            )
+        }
+    }
+}
+
+fn crate_attr(attr: TokenStream, path: &syn::Path, default: &str) -> TokenStream {
+    let path = path_to_string(path);
+    if path == default {
+        quote!()
+    } else {
+        quote!(#[#attr(crate = #path)])
+    }
+}
+
+pub(crate) fn path_to_string(path: &syn::Path) -> String {
+    quote!(#path).to_string().replace(' ', "")
 }
 
 #[derive(Clone, Default)]
@@ -101,71 +564,250 @@ pub struct TypeDefOpts {
     pub packed: bool,
     pub zero_copy: bool,
     pub custom_attr: Vec<Meta>,
+    /// Field type overrides by field name.
+    pub field_types: BTreeMap<String, syn::Type>,
+    /// IDL names of the unit enums, stored as `u8` in zero-copy types.
+    pub unit_enums: BTreeSet<String>,
 }
 
 pub struct Generator {
-    pub cargo_manifest_dir: String,
+    pub base_dir: PathBuf,
     pub out_dir: Option<String>,
     pub out_mod: Option<String>,
-    pub idl_file: String,
+    pub idl_file: Option<PathBuf>,
     pub idl: Idl,
+    pub builders: bool,
+    pub gen_tests: bool,
+    pub fuzz: bool,
+    pub processor: bool,
+    pub introspection: bool,
+    pub features: FeatureOptions,
+    pub known_accounts: BTreeMap<String, String>,
+    pub paths: CratePaths,
     pub typedef_opts: BTreeMap<Ident, TypeDefOpts>,
+    /// Instruction args options by instruction name.
+    pub ix_opts: BTreeMap<String, TypeDefOpts>,
+    /// Add `Unknown` variant to the instruction enum (some instructions are filtered out).
+    pub unknown_instruction: bool,
     pub account_type_idx_by_name: BTreeMap<String, usize>,
 }
 
 impl From<GeneratorOptions> for Generator {
     fn from(opt: GeneratorOptions) -> Self {
         let cargo_manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+        Self::new(opt, cargo_manifest_dir)
+    }
+}
+
+impl Generator {
+    /// Creates generator with the IDL and `out_dir` paths relative to `base_dir`.
+    ///
+    /// Panics on error, see [`Generator::try_new`].
+    pub fn new(opt: GeneratorOptions, base_dir: impl Into<PathBuf>) -> Self {
+        Self::try_new(opt, base_dir).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Creates generator with the IDL and `out_dir` paths relative to `base_dir`.
+    pub fn try_new(
+        opt: GeneratorOptions,
+        base_dir: impl Into<PathBuf>,
+    ) -> Result<Self, GenerateError> {
+        let base_dir = base_dir.into();
+        let idl_file = base_dir.join(&opt.idl);
+        let idl = fs::read(&idl_file).map_err(|source| GenerateError::Io {
+            path: idl_file.clone(),
+            source,
+        })?;
+
+        let mut gen = Self::with_idl_bytes(opt, &idl)?;
+        gen.base_dir = base_dir;
+        gen.idl_file = Some(idl_file);
+        Ok(gen)
+    }
+
+    /// Creates generator with the in-memory IDL JSON, `idl` option is ignored.
+    pub fn with_idl_str(opt: GeneratorOptions, idl: &str) -> Result<Self, GenerateError> {
+        Self::with_idl_bytes(opt, idl.as_bytes())
+    }
+
+    /// Creates generator with the in-memory IDL JSON or IDL account dump
+    /// (see [`idl::idl_json`]), `idl` option is ignored.
+    pub fn with_idl_bytes(opt: GeneratorOptions, idl: &[u8]) -> Result<Self, GenerateError> {
+        let (idl, legacy) = parse_idl_spec(idl)?;
+        Self::with_idl_spec(opt, idl, legacy)
+    }
+
+    /// Creates generator with the parsed IDL of the current spec, `idl` option is ignored.
+    ///
+    /// `out_dir` is relative to the current dir.
+    pub fn with_idl(opt: GeneratorOptions, idl: Idl) -> Result<Self, GenerateError> {
+        Self::with_idl_spec(opt, idl, false)
+    }
 
-        let idl_path = PathBuf::from(cargo_manifest_dir.clone()).join(&opt.idl);
-        let idl = load_idl(idl_path);
+    /// Creates generator with the parsed IDL, `legacy` if it's converted from the legacy spec,
+    /// so [`DEFAULT_KNOWN_ACCOUNTS`] are applied.
+    fn with_idl_spec(
+        opt: GeneratorOptions,
+        mut idl: Idl,
+        legacy: bool,
+    ) -> Result<Self, GenerateError> {
+        let paths = CratePaths::new(&opt);
 
         let mut typedef_opts = BTreeMap::new();
 
-        let manually_with_borsh = pathlist_to_idents(opt.with_borsh.as_ref());
-        let manually_zero_copy = pathlist_to_idents(opt.zero_copy.as_ref());
-        let manually_packed = pathlist_to_idents(opt.packed.as_ref());
-        let manually_repr = opt.attr.iter().fold(HashMap::new(), |mut out, attr_opts| {
-            let names = pathlist_to_idents(Some(&attr_opts.names));
+        let manually_with_borsh = pathlist_to_idents(opt.with_borsh.as_ref())?;
+        let manually_zero_copy = pathlist_to_idents(opt.zero_copy.as_ref())?;
+        let manually_packed = pathlist_to_idents(opt.packed.as_ref())?;
+        let mut manually_repr = HashMap::new();
+        for attr_opts in &opt.attr {
+            let names = pathlist_to_idents(Some(&attr_opts.names))?;
             names.iter().for_each(|&name| {
                 attr_opts.attr.iter().for_each(|attr| {
-                    out.entry(name)
+                    manually_repr
+                        .entry(name)
                         .and_modify(|reprs: &mut Vec<Meta>| {
                             reprs.push(attr.clone());
                         })
                         .or_insert(vec![attr.clone()]);
                 })
             });
-            out
-        });
-        idl.types.iter().for_each(|ty| {
+        }
+        let unit_enums: BTreeSet<_> = idl
+            .types
+            .iter()
+            .filter(|ty| !paths.extern_types.contains_key(&item_gen(&ty.name)))
+            .filter(|ty| match &ty.ty {
+                IdlTypeDefTy::Enum { variants } => {
+                    (1..=256).contains(&variants.len())
+                        && variants.iter().all(|var| var.fields.is_none())
+                }
+                _ => false,
+            })
+            .map(|ty| ty.name.clone())
+            .collect();
+        for ty in &idl.types {
             let name = item_gen(&ty.name);
             let zero_copy = match &ty.serialization {
                 anchor_lang_idl::types::IdlSerialization::Borsh => false,
                 anchor_lang_idl::types::IdlSerialization::Bytemuck
                 | anchor_lang_idl::types::IdlSerialization::BytemuckUnsafe => true,
                 anchor_lang_idl::types::IdlSerialization::Custom(custom) => {
-                    todo!("serialization `{custom}` not supported yet")
+                    return Err(GenerateError::UnsupportedSerialization(custom.clone()));
+                }
+                other => {
+                    return Err(GenerateError::UnsupportedSerialization(format!(
+                        "{other:?}"
+                    )));
                 }
-                _ => panic!("unknown serialization `{:?}`", ty.serialization),
             };
             let packed = match &ty.repr {
                 Some(IdlRepr::C(modifier) | IdlRepr::Rust(modifier)) => modifier.packed,
                 _ => false,
             };
+            let zero_copy = manually_zero_copy.contains(&name) || zero_copy;
             typedef_opts.insert(
                 name.clone(),
                 TypeDefOpts {
                     with_borsh: manually_with_borsh.contains(&name),
                     packed: manually_packed.contains(&name) || packed,
-                    zero_copy: manually_zero_copy.contains(&name) || zero_copy,
+                    zero_copy,
                     custom_attr: manually_repr.get(&name).cloned().unwrap_or_default(),
+                    field_types: Default::default(),
+                    unit_enums: if zero_copy {
+                        unit_enums.clone()
+                    } else {
+                        Default::default()
+                    },
                 },
             );
-        });
+        }
+
+        // NOTE: the current spec IDLs have the fixed addresses, e.g. `token_program`
+        // without one is an interface of Token and Token-2022
+        let mut known_accounts: BTreeMap<_, _> = DEFAULT_KNOWN_ACCOUNTS
+            .iter()
+            .filter(|_| legacy)
+            .map(|(name, address)| (name.to_string(), address.to_string()))
+            .collect();
+        for (name, address) in opt.known_accounts.iter().flatten() {
+            if address.is_empty() {
+                known_accounts.remove(&name.to_snake_case());
+            } else {
+                if pubkey_bytes(address).is_none() {
+                    return Err(GenerateError::InvalidKnownAccount {
+                        name: name.clone(),
+                        address: address.clone(),
+                    });
+                }
+                known_accounts.insert(name.to_snake_case(), address.clone());
+            }
+        }
 
         let account_names: BTreeSet<_> = idl.accounts.iter().map(|acc| acc.name.clone()).collect();
 
+        for name in paths.extern_types.keys() {
+            match idl.types.iter().find(|ty| &item_gen(&ty.name) == name) {
+                None => return Err(GenerateError::UnknownType(name.to_string())),
+                Some(ty) if account_names.contains(&ty.name) => {
+                    return Err(GenerateError::ExternAccount(name.to_string()))
+                }
+                Some(_) => {}
+            }
+        }
+
+        let mut ix_opts = BTreeMap::<String, TypeDefOpts>::new();
+        for (key, ty) in opt.field_types.iter().flatten() {
+            let Some((owner, field)) = key.rsplit_once("::") else {
+                return Err(GenerateError::InvalidFieldTypeKey(key.clone()));
+            };
+            let field = field.to_snake_case();
+            let field_names: Vec<_> = if owner.starts_with(char::is_lowercase) {
+                let ix = idl
+                    .instructions
+                    .iter()
+                    .find(|ix| ix.name.to_snake_case() == owner.to_snake_case())
+                    .ok_or_else(|| GenerateError::UnknownInstruction(owner.to_string()))?;
+                ix_opts
+                    .entry(ix.name.clone())
+                    .or_default()
+                    .field_types
+                    .insert(field.clone(), ty.clone());
+                ix.args.iter().map(|arg| arg.name.to_snake_case()).collect()
+            } else {
+                let name = item_gen(owner);
+                let def = idl
+                    .types
+                    .iter()
+                    .find(|ty| item_gen(&ty.name) == name)
+                    .ok_or_else(|| GenerateError::UnknownType(owner.to_string()))?;
+                typedef_opts
+                    .entry(name)
+                    .or_default()
+                    .field_types
+                    .insert(field.clone(), ty.clone());
+                match &def.ty {
+                    IdlTypeDefTy::Struct {
+                        fields: Some(IdlDefinedFields::Named(fields)),
+                    } => fields.iter().map(|f| f.name.to_snake_case()).collect(),
+                    _ => vec![],
+                }
+            };
+            if !field_names.contains(&field) {
+                return Err(GenerateError::UnknownField(key.clone()));
+            }
+        }
+
+        if let Some(program_id) = &opt.program_id {
+            if pubkey_bytes(program_id).is_none() {
+                return Err(GenerateError::InvalidProgramId(program_id.clone()));
+            }
+            idl.address = program_id.clone();
+        }
+
+        let unknown_instruction = filter::filter_idl(&mut idl, &opt, &paths.extern_types)?;
+        instructions::check_instruction_names(&idl.instructions, opt.builders)?;
+
+        let account_names: BTreeSet<_> = idl.accounts.iter().map(|acc| acc.name.clone()).collect();
         let account_type_idx_by_name = idl
             .types
             .iter()
@@ -174,53 +816,141 @@ impl From<GeneratorOptions> for Generator {
             .map(|(idx, ty)| (ty.name.clone(), idx))
             .collect();
 
-        Generator {
-            cargo_manifest_dir,
+        Ok(Generator {
+            base_dir: PathBuf::new(),
             out_dir: opt.out_dir,
             out_mod: opt.out_mod,
-            idl_file: opt.idl.clone(),
+            idl_file: None,
             idl,
+            builders: opt.builders,
+            gen_tests: opt.gen_tests,
+            fuzz: opt.fuzz,
+            processor: opt.processor,
+            introspection: opt.introspection,
+            features: opt.features,
+            known_accounts,
+            paths,
             typedef_opts,
+            ix_opts,
+            unknown_instruction,
             account_type_idx_by_name,
+        })
+    }
+
+    /// Prints `cargo:rerun-if-changed` for the IDL file, if it was loaded from file.
+    pub fn rerun_if_changed(&self) {
+        if let Some(idl_file) = &self.idl_file {
+            println!("cargo:rerun-if-changed={}", idl_file.display());
         }
     }
-}
 
-impl Generator {
     pub fn generate(&self) -> TokenStream {
         let stream = self.gen_program_stream();
+        let idl_tracking = self.idl_tracking_gen();
 
         if self.out_dir.is_some() {
-            let (rs_mod_ident, out_file_full) = self.write_stream_to_file(stream);
+            let (rs_mod_ident, out_file_full) = match self.write_stream_to_file(stream) {
+                Ok(out) => out,
+                Err(err) => {
+                    return syn::Error::new(proc_macro2::Span::call_site(), err).to_compile_error()
+                }
+            };
 
             quote! {
+                #idl_tracking
+
                 #[rustfmt::skip]
                 #[path = #out_file_full]
                 mod #rs_mod_ident;
                 pub use #rs_mod_ident::*;
             }
         } else {
-            stream
+            quote! {
+                #idl_tracking
+                #stream
+            }
         }
     }
 
-    pub fn gen_program_file(&self) {
-        self.write_stream_to_file(self.gen_program_stream());
+    /// Makes the compiler track the IDL file, so its changes trigger regeneration.
+    fn idl_tracking_gen(&self) -> TokenStream {
+        let Some(idl_file) = &self.idl_file else {
+            return quote!();
+        };
+        let idl_file = idl_file.display().to_string();
+        quote! {
+            const _: &[u8] = include_bytes!(#idl_file);
+        }
+    }
+
+    /// Writes the program module to `<out_dir>/<out_mod>.rs`.
+    pub fn gen_program_file(&self) -> Result<(), GenerateError> {
+        self.write_stream_to_file(self.gen_program_stream())
+            .map(drop)
+    }
+
+    /// Generates the formatted program module source.
+    pub fn gen_program_string(&self) -> String {
+        format_stream(self.gen_program_stream())
+    }
+
+    /// Writes the formatted program module to `path`.
+    pub fn write_program_file<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.gen_program_string())
     }
 
     pub fn gen_program_stream(&self) -> TokenStream {
         let macros = self.gen_macros();
         let exports = self.gen_exports();
 
-        let types = self.mod_gen(&format_ident!("types"), Self::gen_types, false, false);
+        let features = &self.features;
+        let types = self.mod_gen(
+            &format_ident!("types"),
+            features.types.as_deref(),
+            Self::gen_types,
+            false,
+            false,
+        );
         let instruction_mod = self.mod_gen(
             &format_ident!("instruction"),
+            features.instruction.as_deref(),
             Self::gen_instructions,
             true,
             true,
         );
-        let state_mod = self.mod_gen(&format_ident!("state"), Self::gen_accounts, true, false);
-        let error_mod = self.mod_gen(&format_ident!("error"), Self::gen_errors, true, false);
+        let state_mod = self.mod_gen(
+            &format_ident!("state"),
+            features.state.as_deref(),
+            Self::gen_accounts,
+            true,
+            false,
+        );
+        let error_mod = self.mod_gen(
+            &format_ident!("error"),
+            features.error.as_deref(),
+            Self::gen_errors,
+            true,
+            false,
+        );
+        let fuzz_mod = self.mod_gen(
+            &format_ident!("fuzz"),
+            features.fuzz.as_deref(),
+            Self::gen_fuzz,
+            false,
+            false,
+        );
+        let processor_mod = self.mod_gen(
+            &format_ident!("processor"),
+            features.processor.as_deref(),
+            Self::gen_processor,
+            true,
+            false,
+        );
+        let tests = self.gen_tests();
 
         quote! {
             #macros
@@ -229,38 +959,66 @@ impl Generator {
             #types
             #state_mod
             #error_mod
+            #fuzz_mod
+            #processor_mod
+            #tests
         }
     }
 
-    fn write_stream_to_file(&self, stream: TokenStream) -> (Ident, String) {
-        let out_dir = self.out_dir.as_ref().expect("out dir not set");
+    pub fn program_id_gen(&self) -> Option<TokenStream> {
+        pubkey_gen(&self.idl.address, &self.paths)
+    }
+
+    /// Program address or `compile_error!` for the IDLs without one.
+    pub fn program_id_or_error_gen(&self) -> TokenStream {
+        self.program_id_gen().unwrap_or_else(program_id_error_gen)
+    }
+
+    /// Program address bytes (`[u8; 32]`) or `compile_error!` for the IDLs without one.
+    pub fn program_id_array_or_error_gen(&self) -> TokenStream {
+        pubkey_array_gen(&self.idl.address).unwrap_or_else(program_id_error_gen)
+    }
+
+    fn write_stream_to_file(&self, stream: TokenStream) -> Result<(Ident, String), GenerateError> {
+        let out_dir = self.out_dir.as_ref().ok_or(GenerateError::OutDirNotSet)?;
 
         let rs_mod_name = self.out_mod.as_deref().unwrap_or("_gen_");
         let rs_mod_ident = format_ident!("{rs_mod_name}");
 
-        let out_dir_full = format!("{}/{out_dir}", self.cargo_manifest_dir);
-        let out_file_full = format!("{out_dir_full}/{rs_mod_name}.rs");
+        let out_dir_full = self.base_dir.join(out_dir);
+        let out_file_full = out_dir_full
+            .join(format!("{rs_mod_name}.rs"))
+            .display()
+            .to_string();
 
-        let raw = stream.to_string();
-        let new = match PrettyPlease::default().format_tokens(stream) {
-            Ok(formated) => formated,
-            Err(err) => format!("compile_error!(\"{err}\");\n\n\n{raw}"),
-        }
-        .into_bytes();
+        let new = format_stream(stream).into_bytes();
 
         let already_generated = fs::read(&out_file_full)
             .map(|old| new == old)
             .unwrap_or_default();
 
         if !already_generated {
-            std::fs::create_dir_all(&out_dir_full).expect("create out dir");
-            fs::write(&out_file_full, new).unwrap();
+            fs::create_dir_all(&out_dir_full).map_err(|source| GenerateError::Io {
+                path: out_dir_full.clone(),
+                source,
+            })?;
+            fs::write(&out_file_full, new).map_err(|source| GenerateError::Io {
+                path: out_file_full.clone().into(),
+                source,
+            })?;
         }
 
-        (rs_mod_ident, out_file_full)
+        Ok((rs_mod_ident, out_file_full))
     }
 
-    fn mod_gen<G>(&self, name: &Ident, gen: G, need_types: bool, need_state: bool) -> TokenStream
+    fn mod_gen<G>(
+        &self,
+        name: &Ident,
+        feature: Option<&str>,
+        gen: G,
+        need_types: bool,
+        need_state: bool,
+    ) -> TokenStream
     where
         G: FnOnce(&Self) -> TokenStream,
     {
@@ -284,7 +1042,9 @@ impl Generator {
                     use super::state::*;
                 }
             };
+            let cfg = cfg_feature_gen(feature);
             quote! {
+                #cfg
                 pub mod #name {
                     #types_import
                     #state_import
@@ -295,14 +1055,59 @@ impl Generator {
     }
 }
 
-fn pathlist_to_idents(list: Option<&PathList>) -> HashSet<&Ident> {
-    list.map(|el| el.iter().map(|el| el.get_ident().unwrap()).collect())
-        .unwrap_or_default()
+fn program_id_error_gen() -> TokenStream {
+    quote! {
+        compile_error!("the IDL has no program address, set it with `program_id = \"...\"`")
+    }
+}
+
+fn pathlist_to_idents(list: Option<&PathList>) -> Result<HashSet<&Ident>, GenerateError> {
+    list.into_iter()
+        .flat_map(|list| list.iter())
+        .map(|path| {
+            path.get_ident()
+                .ok_or_else(|| GenerateError::InvalidName(path_to_string(path)))
+        })
+        .collect()
+}
+
+fn format_stream(stream: TokenStream) -> String {
+    let raw = stream.to_string();
+    match PrettyPlease::default().format_tokens(stream) {
+        Ok(formated) => formated,
+        Err(err) => format!("compile_error!(\"{err}\");\n\n\n{raw}"),
+    }
 }
 
 pub fn load_idl<P: AsRef<Path>>(path: P) -> Idl {
     let path =
         PathBuf::from(env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR")).join(path);
-    let idl = fs::read(path).expect("IDL path");
-    anchor_lang_idl::convert::convert_idl(&idl).expect("IDL data")
+    read_idl(path)
+}
+
+pub fn read_idl<P: AsRef<Path>>(path: P) -> Idl {
+    try_read_idl(path).unwrap_or_else(|err| panic!("{err}"))
+}
+
+pub fn try_read_idl<P: AsRef<Path>>(path: P) -> Result<Idl, GenerateError> {
+    let path = path.as_ref();
+    let idl = fs::read(path).map_err(|source| GenerateError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse_idl(&idl)
+}
+
+/// Parses IDL JSON or IDL account dump (see [`idl::idl_json`]), legacy IDLs are converted.
+pub fn parse_idl(idl: &[u8]) -> Result<Idl, GenerateError> {
+    parse_idl_spec(idl).map(|(idl, _)| idl)
+}
+
+/// Same as [`parse_idl`], also returns whether the IDL is of the legacy spec.
+fn parse_idl_spec(idl: &[u8]) -> Result<(Idl, bool), GenerateError> {
+    let json = idl::idl_json(idl)?;
+    let legacy = idl::is_legacy(&json);
+    let idl = anchor_lang_idl::convert::convert_idl(&idl::with_legacy_address(json))
+        .map_err(|err| GenerateError::Idl(err.to_string()))?;
+    Ok((idl, legacy))
 }
@@ -0,0 +1,392 @@
+use heck::ToSnakeCase;
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+
+use crate::common::{item_gen, Field};
+use crate::instructions::{acc_item_name, arg_binding};
+use crate::Generator;
+
+impl Generator {
+    pub fn processor_name(&self) -> Ident {
+        item_gen(&format!("{}Processor", self.idl.metadata.name))
+    }
+
+    pub fn gen_processor(&self) -> TokenStream {
+        if !self.processor {
+            return quote!();
+        }
+        let paths = &self.paths;
+        let pubkey = paths.pubkey();
+        let account_info = paths.account_info();
+        let program_error = paths.program_error();
+        let master_enum_name = self.master_enum_name();
+        let processor_name = self.processor_name();
+
+        let mut methods = Vec::new();
+        let mut dispatch = Vec::new();
+        for ix in &self.idl.instructions {
+            let name = item_gen(&ix.name);
+            let method = format_ident!("{}", ix.name.to_snake_case());
+            let accounts_name = format_ident!("{name}Accounts");
+            let opts = self.ix_opts.get(&ix.name).cloned().unwrap_or_default();
+            let args: Vec<_> = ix
+                .args
+                .iter()
+                .map(|arg| Field::named(arg, &opts, paths))
+                .collect();
+            let arg_names: Vec<_> = args.iter().map(|arg| &arg.ident).collect();
+            let arg_bindings: Vec<_> = args.iter().map(arg_binding).collect();
+            let arg_decls = args.iter().map(|arg| {
+                let ty = &arg.ty;
+                let name = processor_arg_name(arg);
+                quote!(#name: #ty)
+            });
+            let arg_pattern = if args.is_empty() {
+                quote!()
+            } else {
+                quote!({ #(#arg_names: #arg_bindings),* })
+            };
+            let docs = format!(" Processes [`super::instruction::{name}`].");
+            methods.push(quote! {
+                #[doc = #docs]
+                #[allow(unused_variables, clippy::too_many_arguments)]
+                fn #method(
+                    program_id: &#pubkey,
+                    accounts: #accounts_name<&#account_info>,
+                    remaining_accounts: &[#account_info],
+                    #(#arg_decls,)*
+                ) -> Result<(), #program_error> {
+                    Err(#program_error::InvalidInstructionData)
+                }
+            });
+            dispatch.push(quote! {
+                #master_enum_name::#name #arg_pattern => {
+                    let (accounts, remaining_accounts) = #accounts_name::from_slice(accounts)
+                        .ok_or(#program_error::NotEnoughAccountKeys)?;
+                    P::#method(
+                        program_id,
+                        accounts,
+                        remaining_accounts,
+                        #(#arg_bindings,)*
+                    )
+                }
+            });
+        }
+        if self.unknown_instruction {
+            methods.push(quote! {
+                /// Processes the instruction filtered out of the interface.
+                #[allow(unused_variables)]
+                fn unknown(
+                    program_id: &#pubkey,
+                    accounts: &[#account_info],
+                    discriminator: [u8; 8],
+                    data: Vec<u8>,
+                ) -> Result<(), #program_error> {
+                    Err(#program_error::InvalidInstructionData)
+                }
+            });
+            dispatch.push(quote! {
+                #master_enum_name::Unknown { discriminator, data } => {
+                    P::unknown(program_id, accounts, discriminator, data)
+                }
+            });
+        }
+
+        quote! {
+            use super::instruction::*;
+
+            /// Program implementation with the typed accounts and args of the instructions,
+            /// the not implemented instructions fail with `InvalidInstructionData`.
+            pub trait #processor_name {
+                #(#methods)*
+            }
+
+            /// Program entrypoint dispatching the instruction to `P`, e.g.
+            /// `processor!(process_instruction::<P>)` of `solana-program-test`.
+            pub fn process_instruction<P: #processor_name>(
+                program_id: &#pubkey,
+                accounts: &[#account_info],
+                data: &[u8],
+            ) -> Result<(), #program_error> {
+                match #master_enum_name::unpack(data)
+                    .map_err(|_| #program_error::InvalidInstructionData)?
+                {
+                    #(#dispatch)*
+                }
+            }
+        }
+    }
+
+    /// Tests of the instruction dispatch for the `gen_tests` module.
+    pub fn gen_processor_tests(&self) -> TokenStream {
+        if !self.processor || self.idl.instructions.is_empty() {
+            return quote!();
+        }
+        let paths = &self.paths;
+        let pubkey = paths.pubkey();
+        let account_info = paths.account_info();
+        let program_error = paths.program_error();
+        let master_enum_name = self.master_enum_name();
+        let processor_name = self.processor_name();
+
+        let mut methods = Vec::new();
+        let mut tests = Vec::new();
+        for ix in &self.idl.instructions {
+            let name = item_gen(&ix.name);
+            let method_name = ix.name.to_snake_case();
+            let method = format_ident!("{method_name}");
+            let test_name = format_ident!("{method_name}_dispatch");
+            let accounts_name = format_ident!("{name}Accounts");
+            let opts = self.ix_opts.get(&ix.name).cloned().unwrap_or_default();
+            let args: Vec<_> = ix
+                .args
+                .iter()
+                .map(|arg| Field::named(arg, &opts, paths))
+                .collect();
+            let arg_names: Vec<_> = args.iter().map(|arg| &arg.ident).collect();
+            let arg_bindings: Vec<_> = args.iter().map(arg_binding).collect();
+            let arg_decls = args.iter().zip(&arg_bindings).map(|(arg, binding)| {
+                let ty = &arg.ty;
+                quote!(#binding: #ty)
+            });
+            let arg_pattern = if args.is_empty() {
+                quote!()
+            } else {
+                quote!({ #(#arg_names: #arg_bindings),* })
+            };
+            methods.push(quote! {
+                fn #method(
+                    program_id: &#pubkey,
+                    accounts: #accounts_name<&#account_info>,
+                    remaining_accounts: &[#account_info],
+                    #(#arg_decls,)*
+                ) -> Result<(), #program_error> {
+                    record(
+                        #method_name,
+                        program_id,
+                        accounts.iter().map(|(_, info)| *info),
+                        remaining_accounts,
+                        vec![#(format!("{:?}", #arg_bindings)),*],
+                    )
+                }
+            });
+            let discriminator = &ix.discriminator;
+            let accounts_len = ix
+                .accounts
+                .iter()
+                .flat_map(|acc| acc_item_name(acc, false))
+                .count();
+            // one remaining account
+            let with_remaining = accounts_len + 1;
+            let missing_account = if accounts_len == 0 {
+                quote!()
+            } else {
+                let missing_len = accounts_len - 1;
+                quote! {
+                    assert_eq!(
+                        process(&data, #missing_len).unwrap_err(),
+                        #program_error::NotEnoughAccountKeys
+                    );
+                }
+            };
+            tests.push(quote! {
+                #[test]
+                fn #test_name() {
+                    let data = zeroed_ix_data(&[#(#discriminator),*]);
+                    #[allow(unreachable_patterns)]
+                    let args: Vec<String> = match #master_enum_name::unpack(&data).unwrap() {
+                        #master_enum_name::#name #arg_pattern => {
+                            vec![#(format!("{:?}", #arg_bindings)),*]
+                        }
+                        _ => unreachable!(),
+                    };
+                    let call = process(&data, #with_remaining).unwrap();
+                    assert_eq!(call.method, #method_name);
+                    assert_eq!(call.program_id, program_id());
+                    assert_eq!(call.accounts, keys(#accounts_len));
+                    assert_eq!(call.remaining_accounts, keys(#with_remaining)[#accounts_len..]);
+                    assert_eq!(call.args, args);
+                    #missing_account
+                }
+            });
+        }
+        if let Some(unknown) = self.unknown_discriminator() {
+            methods.push(quote! {
+                fn unknown(
+                    program_id: &#pubkey,
+                    accounts: &[#account_info],
+                    discriminator: [u8; 8],
+                    data: Vec<u8>,
+                ) -> Result<(), #program_error> {
+                    record(
+                        "unknown",
+                        program_id,
+                        accounts.iter(),
+                        &[],
+                        vec![format!("{discriminator:?}"), format!("{:?}", data)],
+                    )
+                }
+            });
+            tests.push(quote! {
+                #[test]
+                fn unknown_dispatch() {
+                    let discriminator = [#(#unknown),*];
+                    let data = zeroed_ix_data(&discriminator);
+                    let call = process(&data, 1).unwrap();
+                    assert_eq!(call.method, "unknown");
+                    assert_eq!(call.accounts, keys(1));
+                    assert_eq!(
+                        call.args,
+                        [format!("{discriminator:?}"), format!("{:?}", &data[8..])]
+                    );
+                }
+            });
+        }
+
+        let types_import = if self.idl.types.is_empty() {
+            quote!()
+        } else {
+            quote! {
+                #[allow(unused_imports)]
+                use super::super::types::*;
+            }
+        };
+        quote! {
+            use super::super::instruction::*;
+            use super::super::processor::*;
+            use super::zeroed_ix_data;
+            #types_import
+
+            /// Processor method call recorded by the `Recorder`.
+            #[derive(Debug)]
+            struct Call {
+                method: &'static str,
+                program_id: #pubkey,
+                accounts: Vec<#pubkey>,
+                remaining_accounts: Vec<#pubkey>,
+                /// `Debug` of the args.
+                args: Vec<String>,
+            }
+
+            thread_local! {
+                static CALL: ::std::cell::RefCell<Option<Call>> = const { ::std::cell::RefCell::new(None) };
+            }
+
+            fn record<'a, 'b: 'a>(
+                method: &'static str,
+                program_id: &#pubkey,
+                accounts: impl Iterator<Item = &'a #account_info<'b>>,
+                remaining_accounts: &[#account_info],
+                args: Vec<String>,
+            ) -> Result<(), #program_error> {
+                let call = Call {
+                    method,
+                    program_id: *program_id,
+                    accounts: accounts.map(|info| *info.key).collect(),
+                    remaining_accounts: remaining_accounts.iter().map(|info| *info.key).collect(),
+                    args,
+                };
+                CALL.with(|recorded| recorded.replace(Some(call)));
+                Ok(())
+            }
+
+            /// Records the called method with its args and accounts.
+            struct Recorder;
+
+            impl #processor_name for Recorder {
+                #(#methods)*
+            }
+
+            fn program_id() -> #pubkey {
+                #pubkey::new_from_array([0xff; 32])
+            }
+
+            /// Distinct keys of the accounts in order.
+            fn keys(len: usize) -> Vec<#pubkey> {
+                (0..len)
+                    .map(|idx| #pubkey::new_from_array([idx as u8; 32]))
+                    .collect()
+            }
+
+            /// Processes the instruction with the given number of accounts,
+            /// returning the call recorded by the processor.
+            fn process(data: &[u8], accounts_len: usize) -> Result<Call, #program_error> {
+                let program_id = program_id();
+                let keys = keys(accounts_len);
+                let mut lamports = vec![0; accounts_len];
+                let mut accounts_data = vec![Vec::new(); accounts_len];
+                let accounts: Vec<_> = keys
+                    .iter()
+                    .zip(&mut lamports)
+                    .zip(&mut accounts_data)
+                    .map(|((key, lamports), data)| {
+                        #account_info::new(key, false, false, lamports, data, &program_id, false, 0)
+                    })
+                    .collect();
+                CALL.with(|recorded| recorded.take());
+                process_instruction::<Recorder>(&program_id, &accounts, data)?;
+                Ok(CALL.with(|recorded| recorded.take()).expect("no processor method called"))
+            }
+
+            #(#tests)*
+        }
+    }
+
+    /// Discriminator of none of the instructions dispatched to `unknown`, if it's set.
+    fn unknown_discriminator(&self) -> Option<[u8; 8]> {
+        if !self.unknown_instruction {
+            return None;
+        }
+        (0..=u8::MAX).map(|byte| [byte; 8]).find(|discriminator| {
+            self.idl
+                .instructions
+                .iter()
+                .all(|ix| ix.discriminator != discriminator)
+        })
+    }
+}
+
+/// Name of the arg in the processor method signature, prefixed if it's named
+/// like the `remaining_accounts` param (`program_id` and `accounts` clash
+/// with the instruction fields and are rejected).
+fn processor_arg_name(arg: &Field) -> Ident {
+    let ident = arg.ident.as_ref().unwrap();
+    if ident == "remaining_accounts" {
+        arg_binding(arg)
+    } else {
+        ident.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::{generator, idl};
+    use crate::GeneratorOptions;
+
+    #[test]
+    fn arg_named_like_param() {
+        let instructions = r#"{
+            "name": "update",
+            "discriminator": [1, 1, 1, 1, 1, 1, 1, 1],
+            "accounts": [{ "name": "authority", "signer": true }],
+            "args": [
+                { "name": "remaining_accounts", "type": "u8" },
+                { "name": "amount", "type": "u64" }
+            ]
+        }"#;
+        let opts = GeneratorOptions::builder().processor(true).build();
+        let processor = generator(opts, &idl(instructions, "", ""))
+            .gen_processor()
+            .to_string();
+        // the signature keeps the param names, the arg is prefixed
+        assert!(processor.contains("fn update (program_id : &"));
+        assert!(processor.contains("arg_remaining_accounts : u8 , amount : u64 ,)"));
+        // the dispatch binds the args to the prefixed locals
+        assert!(processor.contains(
+            "Update { remaining_accounts : arg_remaining_accounts , amount : arg_amount }"
+        ));
+        assert!(processor.contains(
+            "P :: update (program_id , accounts , remaining_accounts , arg_remaining_accounts , arg_amount ,)"
+        ));
+    }
+}
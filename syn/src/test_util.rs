@@ -0,0 +1,37 @@
+//! IDL fixtures of the generator tests.
+
+use crate::{Generator, GeneratorOptions};
+
+/// Address of the test program.
+pub const ADDRESS: &str = "11111111111111111111111111111111";
+
+/// IDL of the `test` program with the JSON lists of the `instructions`,
+/// `accounts` and `types`.
+pub fn idl(instructions: &str, accounts: &str, types: &str) -> String {
+    format!(
+        r#"{{
+            "address": "{ADDRESS}",
+            "metadata": {{ "name": "test", "version": "0.1.0", "spec": "0.1.0" }},
+            "instructions": [{instructions}],
+            "accounts": [{accounts}],
+            "types": [{types}]
+        }}"#
+    )
+}
+
+/// Legacy IDL of the `test` program without the address, with the JSON lists
+/// of the `instructions` and `accounts`.
+pub fn legacy_idl(instructions: &str, accounts: &str) -> String {
+    format!(
+        r#"{{
+            "version": "0.1.0",
+            "name": "test",
+            "instructions": [{instructions}],
+            "accounts": [{accounts}]
+        }}"#
+    )
+}
+
+pub fn generator(opts: GeneratorOptions, idl: &str) -> Generator {
+    Generator::with_idl_str(opts, idl).unwrap()
+}
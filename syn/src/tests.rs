@@ -0,0 +1,88 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::common::cfg_feature_gen;
+use crate::Generator;
+
+impl Generator {
+    pub fn gen_tests(&self) -> TokenStream {
+        if !self.gen_tests {
+            return quote!();
+        }
+        let instruction_tests = self.gen_instruction_tests();
+        let processor_tests = self.gen_processor_tests();
+        // shared by the instruction and processor tests
+        let ix_data_fixture = if instruction_tests.is_empty() && processor_tests.is_empty() {
+            quote!()
+        } else {
+            let cfg = cfg_any_feature_gen(&[
+                self.features.instruction.as_deref(),
+                self.features.processor.as_deref(),
+            ]);
+            quote! {
+                /// Max size of the instruction data, limited by the transaction size.
+                #cfg
+                const IX_DATA_LEN: usize = 1232;
+
+                /// Max size instruction data with the discriminator and zeroed args.
+                #cfg
+                fn zeroed_ix_data(discriminator: &[u8]) -> Vec<u8> {
+                    let mut data = vec![0; IX_DATA_LEN];
+                    data[..discriminator.len()].copy_from_slice(discriminator);
+                    data
+                }
+            }
+        };
+        let instruction_tests = test_mod_gen(
+            quote!(instruction),
+            self.features.instruction.as_deref(),
+            instruction_tests,
+        );
+        let state_tests = test_mod_gen(
+            quote!(state),
+            self.features.state.as_deref(),
+            self.gen_account_tests(),
+        );
+        let processor_tests = test_mod_gen(
+            quote!(processor),
+            self.features.processor.as_deref(),
+            processor_tests,
+        );
+        let fuzz_tests = test_mod_gen(
+            quote!(fuzz),
+            self.features.fuzz.as_deref(),
+            self.gen_fuzz_tests(),
+        );
+        quote! {
+            #[cfg(test)]
+            mod tests {
+                #ix_data_fixture
+                #instruction_tests
+                #state_tests
+                #fuzz_tests
+                #processor_tests
+            }
+        }
+    }
+}
+
+fn test_mod_gen(name: TokenStream, feature: Option<&str>, tests: TokenStream) -> TokenStream {
+    if tests.is_empty() {
+        return quote!();
+    }
+    let cfg = cfg_feature_gen(feature);
+    quote! {
+        #cfg
+        mod #name {
+            #tests
+        }
+    }
+}
+
+/// `cfg` enabled by any of the features, none if any item isn't gated.
+fn cfg_any_feature_gen(features: &[Option<&str>]) -> TokenStream {
+    let Some(features) = features.iter().copied().collect::<Option<Vec<_>>>() else {
+        return quote!();
+    };
+    quote!(#[cfg(any(#(feature = #features),*))])
+}
@@ -1,11 +1,15 @@
 use std::collections::BTreeMap;
 
-use anchor_lang_idl::types::{IdlDefinedFields, IdlSerialization, IdlTypeDef, IdlTypeDefTy};
+use anchor_lang_idl::types::{
+    IdlDefinedFields, IdlSerialization, IdlType, IdlTypeDef, IdlTypeDefTy,
+};
+use heck::ToSnakeCase;
 use proc_macro2::{Ident, TokenStream};
-use quote::quote;
+use quote::{format_ident, quote};
 
 use crate::common::*;
-use crate::{Generator, TypeDefOpts};
+use crate::layout::{TypeLayouts, ON_CHAIN_INT128_ALIGN};
+use crate::{CratePaths, Generator, TypeDefOpts};
 
 impl Generator {
     pub fn gen_types(&self) -> TokenStream {
@@ -14,7 +18,28 @@ impl Generator {
             .types
             .iter()
             .filter(|ty| !self.account_type_idx_by_name.contains_key(&ty.name))
-            .map(|ty| typedef_gen(&self.idl.types, &self.typedef_opts, ty).0);
+            .filter(|ty| !self.paths.extern_types.contains_key(&item_gen(&ty.name)))
+            .map(|ty| {
+                let event_docs = self
+                    .idl
+                    .events
+                    .iter()
+                    .find(|event| event.name == ty.name)
+                    .map(|event| discriminator_docs_gen(&event.discriminator))
+                    .unwrap_or_default();
+                let (typedef, _, opts) = typedef_gen(
+                    &self.idl.types,
+                    &self.typedef_opts,
+                    &self.paths,
+                    ty,
+                    event_docs,
+                );
+                let arbitrary = self.arbitrary_typedef_gen(ty, &opts);
+                quote! {
+                    #typedef
+                    #arbitrary
+                }
+            });
         quote!(#(#types)*)
     }
 }
@@ -22,11 +47,16 @@ impl Generator {
 pub fn typedef_gen(
     defs: &[IdlTypeDef],
     opts: &BTreeMap<Ident, TypeDefOpts>,
+    paths: &CratePaths,
     ty: &IdlTypeDef,
+    extra_docs: TokenStream,
 ) -> (TokenStream, Ident, TypeDefOpts) {
     let docs = docs_gen(&ty.docs);
+    let docs = quote!(#docs #extra_docs);
     let name = item_gen(&ty.name);
+    let opts_by_name = opts;
     let opts = opts.get(&name).cloned().unwrap_or_default();
+    let extern_types = &paths.extern_types;
     let derive_debug = if opts.packed {
         // NOTE: pointers to the fields of the boxed structure are not aligned,
         // so they need to be copied to a local variable,
@@ -37,14 +67,18 @@ pub fn typedef_gen(
     };
     let typedef = match &ty.ty {
         IdlTypeDefTy::Struct { fields } => {
-            let (derive, attributes) = get_def_field_list_properties(defs, fields)
-                .derive_and_attrs(&opts, &ty.serialization, &ty.repr);
-            let fields = pub_def_fields_decl_gen(fields, &opts);
+            let (derive, attributes) = get_def_field_list_properties(defs, extern_types, fields)
+                .derive_and_attrs(&opts, &ty.serialization, &ty.repr, paths);
+            let field_types_asserts = field_types_asserts_gen(&name, fields, &opts, paths);
+            let layout_assert = layout_assert_gen(defs, opts_by_name, ty, &opts);
+            let accessors = accessors_gen(&name, defs, fields, &opts, paths);
+            let fields = pub_def_fields_decl_gen(fields, &opts, paths);
             let unsafe_bytemuck_impls =
                 if matches!(ty.serialization, IdlSerialization::BytemuckUnsafe) {
+                    let bytemuck = &paths.bytemuck;
                     quote! {
-                        unsafe impl ::bytemuck::Pod for #name {}
-                        unsafe impl ::bytemuck::Zeroable for #name {}
+                        unsafe impl #bytemuck::Pod for #name {}
+                        unsafe impl #bytemuck::Zeroable for #name {}
                     }
                 } else {
                     quote!()
@@ -56,29 +90,30 @@ pub fn typedef_gen(
                 #(#attributes)*
                 pub struct #name #fields
                 #unsafe_bytemuck_impls
+                #field_types_asserts
+                #layout_assert
+                #accessors
             }
         }
         IdlTypeDefTy::Enum { variants } => {
-            let (derive, attributes) = get_variant_list_properties(defs, variants)
+            let (derive, attributes) = get_variant_list_properties(defs, extern_types, variants)
                 .whitout_default()
-                .derive_and_attrs(&opts, &ty.serialization, &ty.repr);
+                .derive_and_attrs(&opts, &ty.serialization, &ty.repr, paths);
+            // NOTE: the IDL variants have no docs
             let variants = variants.iter().map(|var| {
                 let name = item_gen(&var.name);
                 let fields = match &var.fields {
                     Some(IdlDefinedFields::Named(fields)) => {
-                        let fields = fields_decl_gen(fields, &opts);
+                        let fields = fields_decl_gen(fields, &opts, paths);
                         quote!({ #fields })
                     }
                     Some(IdlDefinedFields::Tuple(types)) => {
-                        let types = types_gen(types, &opts);
+                        let types = types_gen(types, &opts, paths);
                         quote!(( #types ))
                     }
                     None => quote!(),
                 };
-                quote! {
-                    #docs
-                    #name #fields
-                }
+                quote!(#name #fields)
             });
             quote! {
                 #docs
@@ -91,10 +126,10 @@ pub fn typedef_gen(
             }
         }
         IdlTypeDefTy::Type { alias } => {
-            let (_derive, attributes) = get_type_properties(defs, alias)
+            let (_derive, attributes) = get_type_properties(defs, extern_types, alias)
                 .whitout_default()
-                .derive_and_attrs(&opts, &ty.serialization, &ty.repr);
-            let alias = type_gen(alias, &opts);
+                .derive_and_attrs(&opts, &ty.serialization, &ty.repr, paths);
+            let alias = type_gen(alias, &opts, paths);
             quote! {
                 #docs
                 #(#attributes)*
@@ -104,3 +139,228 @@ pub fn typedef_gen(
     };
     (typedef, name, opts)
 }
+
+/// Compile-time checks that the overridden field types of zero-copy struct keep its layout.
+fn field_types_asserts_gen(
+    name: &Ident,
+    fields: &Option<IdlDefinedFields>,
+    opts: &TypeDefOpts,
+    paths: &CratePaths,
+) -> TokenStream {
+    let (true, Some(IdlDefinedFields::Named(fields))) = (opts.zero_copy, fields) else {
+        return quote!();
+    };
+    let asserts = fields.iter().filter_map(|field| {
+        let field_name = field.name.to_snake_case();
+        let field_type = opts.field_types.get(&field_name)?;
+        let ty = type_gen(&field.ty, opts, paths);
+        let size_msg = format!("`{name}::{field_name}` type override size mismatch");
+        let align_assert = if opts.packed {
+            quote!()
+        } else {
+            let align_msg = format!("`{name}::{field_name}` type override alignment mismatch");
+            quote! {
+                const _: () = assert!(
+                    ::core::mem::align_of::<#field_type>() == ::core::mem::align_of::<#ty>(),
+                    #align_msg
+                );
+            }
+        };
+        Some(quote! {
+            const _: () = assert!(
+                ::core::mem::size_of::<#field_type>() == ::core::mem::size_of::<#ty>(),
+                #size_msg
+            );
+            #align_assert
+        })
+    });
+    quote!(#(#asserts)*)
+}
+
+/// Compile-time check that the zero-copy struct has the IDL layout,
+/// which catches e.g. extern types of a different size.
+fn layout_assert_gen(
+    defs: &[IdlTypeDef],
+    opts_by_name: &BTreeMap<Ident, TypeDefOpts>,
+    ty: &IdlTypeDef,
+    opts: &TypeDefOpts,
+) -> TokenStream {
+    if !opts.zero_copy {
+        return quote!();
+    }
+    let packed = |name: &str| {
+        opts_by_name
+            .get(&item_gen(name))
+            .is_some_and(|opts| opts.packed)
+    };
+    let idl_ty = IdlType::Defined {
+        name: ty.name.clone(),
+        generics: vec![],
+    };
+    // NOTE: `u128` is 8 bytes aligned on-chain, but 16 bytes on the most hosts
+    let size = |int128_align| {
+        TypeLayouts::new(defs, int128_align)
+            .pod(&idl_ty, &packed)
+            .map(|(size, _)| size)
+    };
+    let (Ok(on_chain_size), Ok(host_size)) = (size(ON_CHAIN_INT128_ALIGN), size(16)) else {
+        return quote!();
+    };
+    let name = item_gen(&ty.name);
+    let msg = format!("`{name}` size differs from the IDL layout");
+    let expected = if on_chain_size == host_size {
+        quote!(#on_chain_size)
+    } else {
+        quote! {
+            if ::core::mem::align_of::<u128>() == #ON_CHAIN_INT128_ALIGN {
+                #on_chain_size
+            } else {
+                #host_size
+            }
+        }
+    };
+    quote! {
+        const _: () = assert!(::core::mem::size_of::<#name>() == #expected, #msg);
+    }
+}
+
+/// Typed accessors of the zero-copy struct fields stored as `u8` (`bool` and unit enums).
+///
+/// NOTE: the fields are copied by value, so no references to the fields
+/// of the packed struct are taken.
+fn accessors_gen(
+    name: &Ident,
+    defs: &[IdlTypeDef],
+    fields: &Option<IdlDefinedFields>,
+    opts: &TypeDefOpts,
+    paths: &CratePaths,
+) -> TokenStream {
+    let (true, Some(IdlDefinedFields::Named(fields))) = (opts.zero_copy, fields) else {
+        return quote!();
+    };
+    let anchor_interface = &paths.anchor_interface;
+    let accessors: Vec<_> = fields
+        .iter()
+        .filter_map(|field| {
+            let field_name = field.name.to_snake_case();
+            if opts.field_types.contains_key(&field_name) {
+                return None;
+            }
+            let docs = docs_gen(&field.docs);
+            let ident = format_ident!("{field_name}");
+            let setter = format_ident!("set_{field_name}");
+            match &field.ty {
+                IdlType::Bool => {
+                    let getter = if field_name.starts_with("is_") {
+                        ident.clone()
+                    } else {
+                        format_ident!("is_{field_name}")
+                    };
+                    Some(quote! {
+                        #docs
+                        pub fn #getter(&self) -> bool {
+                            let value = self.#ident;
+                            value != 0
+                        }
+                        #docs
+                        pub fn #setter(&mut self, value: bool) {
+                            self.#ident = value as u8;
+                        }
+                    })
+                }
+                IdlType::Defined { name, .. } if opts.unit_enums.contains(name) => {
+                    let ty = item_gen(name);
+                    let def = defs.iter().find(|def| &def.name == name)?;
+                    let IdlTypeDefTy::Enum { variants } = &def.ty else {
+                        return None;
+                    };
+                    // NOTE: `0..len as u8` is empty for 256 variants
+                    let values = (0..=u8::MAX).take(variants.len());
+                    let variants = variants.iter().map(|var| item_gen(&var.name));
+                    Some(quote! {
+                        #docs
+                        pub fn #ident(&self) -> Result<#ty, #anchor_interface::errors::InvalidEnumValue> {
+                            let value = self.#ident;
+                            match value {
+                                #(#values => Ok(#ty::#variants),)*
+                                _ => Err(#anchor_interface::errors::InvalidEnumValue {
+                                    name: stringify!(#ty),
+                                    value,
+                                }),
+                            }
+                        }
+                        #docs
+                        pub fn #setter(&mut self, value: #ty) {
+                            self.#ident = value as u8;
+                        }
+                    })
+                }
+                _ => None,
+            }
+        })
+        .collect();
+    if accessors.is_empty() {
+        return quote!();
+    }
+    quote! {
+        impl #name {
+            #(#accessors)*
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::{generator, idl};
+    use crate::{parse_path_list, GeneratorOptions};
+
+    /// Zero-copy `Pool` with the `kind` field of the unit enum with `variants`.
+    fn pool_types(variants: usize) -> String {
+        let variants: Vec<_> = (0..variants)
+            .map(|idx| format!(r#"{{ "name": "V{idx}" }}"#))
+            .collect();
+        let types = format!(
+            r#"
+                {{
+                    "name": "Pool",
+                    "type": {{
+                        "kind": "struct",
+                        "fields": [{{ "name": "kind", "type": {{ "defined": {{ "name": "Kind" }} }} }}]
+                    }}
+                }},
+                {{
+                    "name": "Kind",
+                    "type": {{ "kind": "enum", "variants": [{}] }}
+                }}
+            "#,
+            variants.join(",")
+        );
+        let opts = GeneratorOptions {
+            zero_copy: Some(parse_path_list(&["Pool"])),
+            ..Default::default()
+        };
+        generator(opts, &idl("", "", &types))
+            .gen_types()
+            .to_string()
+    }
+
+    #[test]
+    fn unit_enum_accessor_values() {
+        let types = pool_types(3);
+        assert!(types.contains("2u8 => Ok (Kind :: V2)"));
+        assert!(!types.contains("3u8 =>"));
+    }
+
+    #[test]
+    fn unit_enum_accessor_256_variants() {
+        let types = pool_types(256);
+        assert!(types.contains("0u8 => Ok (Kind :: V0)"));
+        assert!(types.contains("255u8 => Ok (Kind :: V255)"));
+    }
+
+    #[test]
+    fn enum_variants_without_docs() {
+        let types = pool_types(2);
+        assert!(types.contains("pub enum Kind { V0 , V1 }"));
+    }
+}
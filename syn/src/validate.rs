@@ -0,0 +1,530 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use anchor_lang_idl::types::{IdlAccount, IdlType, IdlTypeDef, IdlTypeDefTy};
+
+use crate::common::item_gen;
+use crate::filter::typedef_defined_names;
+use crate::idl::account_dump_data;
+use crate::layout::{TypeLayouts, ON_CHAIN_INT128_ALIGN};
+use crate::{GenerateError, Generator};
+
+/// Layout of the account data following the discriminator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    Borsh,
+    ZeroCopy { packed: bool },
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Layout::Borsh => write!(f, "borsh"),
+            Layout::ZeroCopy { packed: false } => write!(f, "zero_copy"),
+            Layout::ZeroCopy { packed: true } => write!(f, "zero_copy, packed"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SampleStatus {
+    /// Decoded consuming every byte.
+    Ok,
+    /// Data length differs from the zero-copy account size.
+    LengthMismatch { expected: usize, got: usize },
+    /// Borsh decoding left some bytes, not all of them zero padding.
+    TrailingBytes(usize),
+    /// Can't be decoded.
+    Invalid(String),
+}
+
+/// Account dump checked against the account layout.
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub path: PathBuf,
+    pub len: usize,
+    pub status: SampleStatus,
+}
+
+impl Sample {
+    pub fn is_ok(&self) -> bool {
+        self.status == SampleStatus::Ok
+    }
+}
+
+impl fmt::Display for Sample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path.display();
+        let len = self.len;
+        match &self.status {
+            SampleStatus::Ok => write!(f, "ok: {path}, {len} bytes"),
+            SampleStatus::LengthMismatch { expected, got } => {
+                write!(
+                    f,
+                    "length mismatch: {path}, {got} bytes, expected {expected}"
+                )
+            }
+            SampleStatus::TrailingBytes(trailing) => {
+                write!(
+                    f,
+                    "trailing bytes: {path}, {trailing} of {len} bytes not decoded"
+                )
+            }
+            SampleStatus::Invalid(err) => write!(f, "invalid: {path}, {err}"),
+        }
+    }
+}
+
+/// Generator options the account samples line up with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Suggestion {
+    pub layout: Layout,
+    /// The account type and the structs it contains.
+    pub types: Vec<String>,
+}
+
+impl fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let types = self.types.join(", ");
+        match self.layout {
+            Layout::Borsh => write!(f, "no zero_copy/packed for {types}"),
+            Layout::ZeroCopy { packed: false } => write!(f, "zero_copy({types})"),
+            Layout::ZeroCopy { packed: true } => {
+                write!(f, "zero_copy({types}), packed({types})")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AccountReport {
+    pub name: String,
+    /// Layout of the account with the generator options.
+    pub layout: Layout,
+    /// On-chain data length (with the discriminator) of the zero-copy account.
+    pub expected_len: Option<usize>,
+    pub samples: Vec<Sample>,
+    /// Layouts every sample lines up with, if the current one doesn't.
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl AccountReport {
+    pub fn is_ok(&self) -> bool {
+        self.samples.iter().all(Sample::is_ok)
+    }
+}
+
+impl fmt::Display for AccountReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.layout)?;
+        if let Some(expected_len) = self.expected_len {
+            write!(f, ", {expected_len} bytes")?;
+        }
+        writeln!(f)?;
+        if self.samples.is_empty() {
+            writeln!(f, "  no samples")?;
+        }
+        for sample in &self.samples {
+            writeln!(f, "  {sample}")?;
+        }
+        for suggestion in &self.suggestions {
+            writeln!(f, "  suggested: {suggestion}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Dump not matching any account.
+#[derive(Clone, Debug)]
+pub struct UnmatchedDump {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct ValidationReport {
+    pub accounts: Vec<AccountReport>,
+    pub unmatched: Vec<UnmatchedDump>,
+}
+
+impl ValidationReport {
+    /// Every sample is decoded consuming every byte.
+    pub fn is_ok(&self) -> bool {
+        self.accounts.iter().all(AccountReport::is_ok)
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for account in &self.accounts {
+            write!(f, "{account}")?;
+        }
+        if !self.unmatched.is_empty() {
+            writeln!(f, "unmatched dumps:")?;
+        }
+        for dump in &self.unmatched {
+            writeln!(f, "  {}: {}", dump.path.display(), dump.reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl Generator {
+    /// Checks the account layouts against the account dumps in `dir`
+    /// (raw account data or `solana account --output json` output).
+    pub fn validate_accounts<P: AsRef<Path>>(
+        &self,
+        dir: P,
+    ) -> Result<ValidationReport, GenerateError> {
+        let io_err = |path: &Path| {
+            let path = path.to_path_buf();
+            move |source| GenerateError::Io { path, source }
+        };
+        let dir = dir.as_ref();
+        let mut paths = fs::read_dir(dir)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|entry| entry.path()))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(io_err(dir))?;
+        paths.retain(|path| path.is_file());
+        paths.sort();
+        let dumps = paths
+            .into_iter()
+            .map(|path| {
+                let data = fs::read(&path).map_err(io_err(&path))?;
+                Ok((path, data))
+            })
+            .collect::<Result<Vec<_>, GenerateError>>()?;
+        Ok(self.validate_dumps(dumps))
+    }
+
+    /// Checks the account layouts against the account dumps, matched by discriminator.
+    pub fn validate_dumps(
+        &self,
+        dumps: impl IntoIterator<Item = (PathBuf, Vec<u8>)>,
+    ) -> ValidationReport {
+        let mut samples = vec![Vec::new(); self.idl.accounts.len()];
+        let mut unmatched = Vec::new();
+        for (path, dump) in dumps {
+            let data = match account_dump_data(&dump) {
+                Ok(data) => data,
+                Err(reason) => {
+                    unmatched.push(UnmatchedDump { path, reason });
+                    continue;
+                }
+            };
+            let account = self
+                .idl
+                .accounts
+                .iter()
+                .position(|acc| data.starts_with(&acc.discriminator));
+            match account {
+                Some(idx) => samples[idx].push((path, data)),
+                None => {
+                    let discriminator = &data[..data.len().min(8)];
+                    unmatched.push(UnmatchedDump {
+                        path,
+                        reason: format!(
+                            "discriminator `{discriminator:?}` doesn't match any account"
+                        ),
+                    });
+                }
+            }
+        }
+        let accounts = self
+            .idl
+            .accounts
+            .iter()
+            .zip(samples)
+            .map(|(acc, samples)| self.account_report(acc, &samples))
+            .collect();
+        ValidationReport {
+            accounts,
+            unmatched,
+        }
+    }
+
+    fn account_report(&self, acc: &IdlAccount, samples: &[(PathBuf, Vec<u8>)]) -> AccountReport {
+        let name = item_gen(&acc.name);
+        let layout = match self.typedef_opts.get(&name) {
+            Some(opts) if opts.zero_copy => Layout::ZeroCopy {
+                packed: opts.packed,
+            },
+            _ => Layout::Borsh,
+        };
+        let packed = |name: &str| {
+            self.typedef_opts
+                .get(&item_gen(name))
+                .is_some_and(|opts| opts.packed)
+        };
+        let ty = IdlType::Defined {
+            name: acc.name.clone(),
+            generics: vec![],
+        };
+        // NOTE: the dumps have the on-chain layout, but the generated zero-copy structs
+        // have the host one (e.g. 16 bytes aligned `u128`), the data of either is accepted
+        let layouts = TypeLayouts::new(&self.idl.types, ON_CHAIN_INT128_ALIGN);
+        let host_layouts = TypeLayouts::host(&self.idl.types);
+        let expected_len = match layout {
+            Layout::ZeroCopy { .. } => layouts
+                .pod(&ty, &packed)
+                .ok()
+                .map(|(size, _)| acc.discriminator.len() + size),
+            Layout::Borsh => None,
+        };
+        let check = |layout: Layout, packed: &dyn Fn(&str) -> bool, data: &[u8]| {
+            let offset = acc.discriminator.len();
+            match layout {
+                Layout::Borsh => {
+                    let mut end = offset;
+                    match layouts.borsh_decode(&ty, data, &mut end) {
+                        Err(err) => SampleStatus::Invalid(err),
+                        // NOTE: accounts with the fixed `space` are zero padded
+                        Ok(()) if data[end..].iter().any(|b| *b != 0) => {
+                            SampleStatus::TrailingBytes(data.len() - end)
+                        }
+                        Ok(()) => SampleStatus::Ok,
+                    }
+                }
+                Layout::ZeroCopy { .. } => {
+                    match (layouts.pod(&ty, packed), host_layouts.pod(&ty, packed)) {
+                        (Err(err), _) | (_, Err(err)) => SampleStatus::Invalid(err),
+                        (Ok((size, _)), Ok((host_size, _)))
+                            if offset + size != data.len() && offset + host_size != data.len() =>
+                        {
+                            SampleStatus::LengthMismatch {
+                                expected: offset + size,
+                                got: data.len(),
+                            }
+                        }
+                        _ => SampleStatus::Ok,
+                    }
+                }
+            }
+        };
+
+        let samples_report: Vec<_> = samples
+            .iter()
+            .map(|(path, data)| Sample {
+                path: path.clone(),
+                len: data.len(),
+                status: check(layout, &packed, data),
+            })
+            .collect();
+        let suggestions = if samples_report.iter().all(Sample::is_ok) {
+            vec![]
+        } else {
+            [
+                Layout::Borsh,
+                Layout::ZeroCopy { packed: false },
+                Layout::ZeroCopy { packed: true },
+            ]
+            .into_iter()
+            .filter(|&candidate| {
+                let packed = |_: &str| candidate == Layout::ZeroCopy { packed: true };
+                samples
+                    .iter()
+                    .all(|(_, data)| check(candidate, &packed, data) == SampleStatus::Ok)
+            })
+            .map(|layout| Suggestion {
+                layout,
+                types: self.layout_types(&acc.name),
+            })
+            .collect()
+        };
+
+        AccountReport {
+            name: name.to_string(),
+            layout,
+            expected_len,
+            samples: samples_report,
+            suggestions,
+        }
+    }
+
+    /// The struct and the (not extern) structs it contains.
+    fn layout_types(&self, name: &str) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut pending = vec![name];
+        while let Some(name) = pending.pop() {
+            let Some(def) = self.typedef(name) else {
+                continue;
+            };
+            let item = item_gen(name).to_string();
+            if !matches!(def.ty, IdlTypeDefTy::Struct { .. }) || out.contains(&item) {
+                continue;
+            }
+            out.push(item);
+            typedef_defined_names(def, &mut pending);
+        }
+        out
+    }
+
+    /// Not extern IDL type definition.
+    fn typedef(&self, name: &str) -> Option<&IdlTypeDef> {
+        if self.paths.extern_types.contains_key(&item_gen(name)) {
+            return None;
+        }
+        self.idl.types.iter().find(|def| def.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{generator, idl};
+    use crate::{parse_path_list, GeneratorOptions};
+
+    const ACCOUNTS: &str = r#"
+        { "name": "Config", "discriminator": [1, 1, 1, 1, 1, 1, 1, 1] },
+        { "name": "Pool", "discriminator": [2, 2, 2, 2, 2, 2, 2, 2] },
+        { "name": "Tick", "discriminator": [3, 3, 3, 3, 3, 3, 3, 3] }
+    "#;
+
+    const TYPES: &str = r#"
+        {
+            "name": "Config",
+            "type": {
+                "kind": "struct",
+                "fields": [
+                    { "name": "flag", "type": "bool" },
+                    { "name": "items", "type": { "vec": "u64" } }
+                ]
+            }
+        },
+        {
+            "name": "Pool",
+            "serialization": "bytemuck",
+            "repr": { "kind": "c" },
+            "type": {
+                "kind": "struct",
+                "fields": [
+                    { "name": "price", "type": "u128" },
+                    { "name": "amount", "type": "u64" }
+                ]
+            }
+        },
+        {
+            "name": "Tick",
+            "serialization": "bytemuck",
+            "repr": { "kind": "c" },
+            "type": {
+                "kind": "struct",
+                "fields": [
+                    { "name": "price", "type": "u128" },
+                    { "name": "amount", "type": "u64" },
+                    { "name": "bump", "type": "u8" }
+                ]
+            }
+        }
+    "#;
+
+    fn validate(dumps: &[&[u8]]) -> ValidationReport {
+        let opts = GeneratorOptions {
+            zero_copy: Some(parse_path_list(&["Pool", "Tick"])),
+            ..Default::default()
+        };
+        let dumps = dumps
+            .iter()
+            .enumerate()
+            .map(|(idx, dump)| (PathBuf::from(idx.to_string()), dump.to_vec()));
+        generator(opts, &idl("", ACCOUNTS, TYPES)).validate_dumps(dumps)
+    }
+
+    fn statuses(report: &ValidationReport, account: usize) -> Vec<SampleStatus> {
+        let samples = &report.accounts[account].samples;
+        samples.iter().map(|sample| sample.status.clone()).collect()
+    }
+
+    /// `Config` with `flag` and one item.
+    fn config() -> Vec<u8> {
+        let mut data = vec![1; 8];
+        data.push(1);
+        data.extend(1u32.to_le_bytes());
+        data.extend(7u64.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn borsh_exact_and_zero_padded() {
+        let mut padded = config();
+        padded.resize(padded.len() + 100, 0);
+        let report = validate(&[&config(), &padded]);
+        assert_eq!(statuses(&report, 0), [SampleStatus::Ok, SampleStatus::Ok]);
+        assert!(report.is_ok());
+    }
+
+    #[test]
+    fn borsh_trailing_garbage() {
+        let mut data = config();
+        data.extend([0, 0, 5, 0]);
+        let report = validate(&[&data]);
+        assert_eq!(statuses(&report, 0), [SampleStatus::TrailingBytes(4)]);
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn borsh_truncated() {
+        let data = config();
+        let report = validate(&[&data[..data.len() - 1]]);
+        assert!(matches!(
+            statuses(&report, 0)[..],
+            [SampleStatus::Invalid(_)]
+        ));
+    }
+
+    #[test]
+    fn zero_copy_on_chain_and_host_layout() {
+        // `u128` alignment pads `Pool` on the most hosts, but not on-chain
+        let host_size = if std::mem::align_of::<u128>() == 16 {
+            32
+        } else {
+            24
+        };
+        let dump = |size: usize| {
+            let mut data = vec![2; 8];
+            data.resize(8 + size, 0);
+            data
+        };
+        let report = validate(&[&dump(24), &dump(host_size), &dump(23)]);
+        assert_eq!(report.accounts[1].expected_len, Some(8 + 24));
+        assert_eq!(
+            statuses(&report, 1),
+            [
+                SampleStatus::Ok,
+                SampleStatus::Ok,
+                SampleStatus::LengthMismatch {
+                    expected: 8 + 24,
+                    got: 8 + 23
+                }
+            ]
+        );
+        assert!(report.accounts[1].suggestions.is_empty());
+    }
+
+    #[test]
+    fn zero_copy_suggests_packed() {
+        // packed size of `Tick`, 32 bytes on-chain and on the host
+        let mut data = vec![3; 8];
+        data.resize(8 + 25, 0);
+        let report = validate(&[&data]);
+        let layouts: Vec<_> = report.accounts[2]
+            .suggestions
+            .iter()
+            .map(|suggestion| suggestion.layout)
+            .collect();
+        // borsh decodes any 25 bytes too
+        assert_eq!(layouts, [Layout::Borsh, Layout::ZeroCopy { packed: true }]);
+    }
+
+    #[test]
+    fn unmatched_discriminator() {
+        let report = validate(&[&[4; 16]]);
+        assert_eq!(report.unmatched.len(), 1);
+        assert!(report.accounts.iter().all(|acc| acc.samples.is_empty()));
+    }
+}